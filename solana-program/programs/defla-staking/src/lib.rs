@@ -1,67 +1,512 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{self, Mint, Token, TokenAccount, Transfer},
+    metadata::{
+        create_metadata_accounts_v3, mpl_token_metadata::types::DataV2, CreateMetadataAccountsV3,
+        Metadata,
+    },
+    token::{self, spl_token::instruction::AuthorityType, Burn, Mint, MintTo, SetAuthority, Token, TokenAccount, Transfer},
 };
 
 declare_id!("BeZPosPTPhULop1xSSRQua29vBbiu1qdiZeDWniVFiKW");
 
-const DAILY_APR: u64 = 75; // 0.75% = 75 basis points
+// Program that owns DeflationaryTokenConfig accounts
+const DEFLATIONARY_TOKEN_PROGRAM_ID: Pubkey = pubkey!("4hFLbSpLEWEvtw1Q6qPubs2QLAMfdiMafzUhyiifDY8T");
+
 const BASIS_POINTS: u64 = 10000;
 const SECONDS_PER_DAY: i64 = 86400;
-const MIN_STAKE_AMOUNT: u64 = 10_000_000_000; // 10,000 HELL (assuming 6 decimals)
-const MIN_LOCK_PERIOD: i64 = 7 * SECONDS_PER_DAY; // 7 days
-const MAX_LOCK_PERIOD: i64 = 120 * SECONDS_PER_DAY; // 120 days
+const MAX_DAILY_APR: u64 = 1000; // 10% per day
+const MAX_LOCK_PERIOD_LIMIT: i64 = 5 * 365 * SECONDS_PER_DAY; // 5 years
+const MAX_PENALTY_BPS: u64 = 5000; // 50% of principal
+const MAX_MULTIPLIER_POINTS: usize = 8;
+const MAX_MULTIPLIER_BPS: u16 = 50000; // 5.0x
+const MAX_REWARD_STREAMS: usize = 4;
+const ACC_PRECISION: u128 = 1_000_000_000_000;
+const MAX_COMPOUND_BOUNTY_BPS: u16 = 500; // 5% of compounded rewards
+const SHARE_PRICE_PRECISION: u128 = 1_000_000_000;
+const MAX_CHECKPOINTS: usize = 32;
+const MAX_APR_CURVE_POINTS: usize = 8;
+const MAX_VESTING_PENALTY_BPS: u16 = 5000; // 50% of unvested rewards
+const VOTING_POWER_MAX_LOCK: i64 = 365 * SECONDS_PER_DAY; // lock length that earns full voting power
 
 #[program]
 pub mod defla_staking {
     use super::*;
 
-    /// Initialize the staking program
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    /// Initialize the pool registry (program upgrade authority only)
+    pub fn initialize_registry(ctx: Context<InitializeRegistry>) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        registry.admin = ctx.accounts.admin.key();
+        registry.pool_count = 0;
+        registry.bump = ctx.bumps.registry;
+
+        Ok(())
+    }
+
+    /// Initialize a staking pool for a mint. Only the mint's deflationary token
+    /// authority or the registry admin can create it
+    pub fn initialize(ctx: Context<Initialize>, config: PoolConfig) -> Result<()> {
+        validate_pool_config(&config)?;
+
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == ctx.accounts.registry.admin
+                || token_config_authority(&ctx.accounts.token_config)? == Some(authority),
+            StakingError::Unauthorized
+        );
+
+        let clock = Clock::get()?;
+        let registry = &mut ctx.accounts.registry;
+        let pool_entry = &mut ctx.accounts.pool_entry;
+        pool_entry.pool = ctx.accounts.staking_pool.key();
+        pool_entry.defla_mint = ctx.accounts.defla_mint.key();
+        pool_entry.authority = authority;
+        pool_entry.index = registry.pool_count;
+        pool_entry.created_at = clock.unix_timestamp;
+        pool_entry.bump = ctx.bumps.pool_entry;
+        registry.pool_count = registry.pool_count.checked_add(1).unwrap();
+
         let staking_pool = &mut ctx.accounts.staking_pool;
         staking_pool.authority = ctx.accounts.authority.key();
         staking_pool.defla_mint = ctx.accounts.defla_mint.key();
+        staking_pool.config = config;
         staking_pool.total_staked = 0;
         staking_pool.total_stakers = 0;
+        staking_pool.penalty_config = PenaltyConfig::default();
+        staking_pool.total_penalties_burned = 0;
+        staking_pool.total_penalties_redistributed = 0;
+        staking_pool.lock_multipliers = Vec::new();
+        staking_pool.total_rewards_funded = 0;
+        staking_pool.registry_index = pool_entry.index;
+        staking_pool.total_weighted_stake = 0;
+        staking_pool.reward_streams = Vec::new();
+        staking_pool.liquid = LiquidState::default();
+        staking_pool.total_unbonding = 0;
+        staking_pool.pending_unbondings = 0;
+        staking_pool.paused = false;
+        staking_pool.emergency_mode = false;
+        staking_pool.max_total_staked = 0;
+        staking_pool.max_stake_per_wallet = 0;
+        staking_pool.reward_vesting_period = 0;
+        staking_pool.vesting_early_exit_bps = 0;
+        staking_pool.total_escrowed_rewards = 0;
+        staking_pool.target_cap = 0;
+        staking_pool.apr_curve = Vec::new();
+        staking_pool.reward_index = 0;
+        staking_pool.reward_index_updated_at = Clock::get()?.unix_timestamp;
         staking_pool.bump = ctx.bumps.staking_pool;
+
+        let pool_checkpoints = &mut ctx.accounts.pool_checkpoints;
+        pool_checkpoints.pool = staking_pool.key();
+        pool_checkpoints.next_index = 0;
+        pool_checkpoints.checkpoints = Vec::new();
+        pool_checkpoints.bump = ctx.bumps.pool_checkpoints;
+
+        emit!(PoolCreatedEvent {
+            pool: staking_pool.key(),
+            defla_mint: staking_pool.defla_mint,
+            authority,
+            registry_index: pool_entry.index,
+            timestamp: clock.unix_timestamp,
+        });
         
         Ok(())
     }
 
-    /// Stake HELL tokens
-    pub fn stake(
-        ctx: Context<Stake>,
-        amount: u64,
-        lock_period_days: u32,
+    /// Fund the pool's reward vault (anyone can fund)
+    pub fn fund_rewards(ctx: Context<FundRewards>, amount: u64) -> Result<()> {
+        require!(amount > 0, StakingError::InvalidAmount);
+
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.funder_token_account.to_account_info(),
+                to: ctx.accounts.staking_pool_token_account.to_account_info(),
+                authority: ctx.accounts.funder.to_account_info(),
+            },
+        );
+        token::transfer(transfer_ctx, amount)?;
+
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        staking_pool.total_rewards_funded = staking_pool.total_rewards_funded.checked_add(amount).unwrap();
+
+        emit!(RewardsFundedEvent {
+            pool: staking_pool.key(),
+            funder: ctx.accounts.funder.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Update pool parameters (authority only). Changes apply to new stakes only
+    pub fn update_pool_config(ctx: Context<UpdatePool>, config: PoolConfig) -> Result<()> {
+        validate_pool_config(&config)?;
+
+        let clock = Clock::get()?;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+
+        // Credit liquid holders at the old rate before it changes
+        accrue_liquid_rewards(staking_pool, clock.unix_timestamp)?;
+        let old_config = staking_pool.config;
+        staking_pool.config = config;
+
+        emit!(PoolConfigUpdatedEvent {
+            pool: staking_pool.key(),
+            old_config,
+            new_config: config,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Pause or resume staking and reward claims (authority only)
+    pub fn set_paused(ctx: Context<UpdatePool>, paused: bool) -> Result<()> {
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        staking_pool.paused = paused;
+
+        emit!(PoolPausedEvent {
+            pool: staking_pool.key(),
+            authority: ctx.accounts.authority.key(),
+            paused,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Enter or leave emergency mode (authority only). While active, staking and
+    /// claims are blocked, liquid shares stop accruing, stakers can pull their principal
+    /// with `emergency_withdraw` and liquid holders redeem with `emergency_liquid_redeem`
+    pub fn set_emergency_mode(ctx: Context<UpdatePool>, enabled: bool) -> Result<()> {
+        let clock = Clock::get()?;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        // Settle liquid rewards up to the switch so the emergency period earns nothing
+        accrue_liquid_rewards(staking_pool, clock.unix_timestamp)?;
+        staking_pool.emergency_mode = enabled;
+
+        emit!(EmergencyModeEvent {
+            pool: staking_pool.key(),
+            authority: ctx.accounts.authority.key(),
+            enabled,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set the utilization-based APR curve (authority only). An empty curve or zero
+    /// target cap uses the fixed `daily_apr`. Switching between fixed and dynamic
+    /// rates is only allowed while the pool is empty
+    pub fn set_apr_curve(
+        ctx: Context<UpdatePool>,
+        target_cap: u64,
+        apr_curve: Vec<AprCurvePoint>,
+    ) -> Result<()> {
+        require!(apr_curve.len() <= MAX_APR_CURVE_POINTS, StakingError::InvalidAprCurve);
+        for (i, point) in apr_curve.iter().enumerate() {
+            require!(
+                point.utilization_bps as u64 <= BASIS_POINTS && point.daily_apr <= MAX_DAILY_APR,
+                StakingError::InvalidAprCurve
+            );
+            if i > 0 {
+                require!(
+                    point.utilization_bps > apr_curve[i - 1].utilization_bps,
+                    StakingError::InvalidAprCurve
+                );
+            }
+        }
+
+        let clock = Clock::get()?;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        let dynamic_after = !apr_curve.is_empty() && target_cap > 0;
+        if dynamic_after != staking_pool.dynamic_apr_enabled() {
+            require!(
+                staking_pool.total_stakers == 0 && staking_pool.liquid.total_shares == 0,
+                StakingError::PoolNotEmpty
+            );
+        }
+
+        // Settle rewards, including liquid holders', at the old rate before switching
+        accrue_liquid_rewards(staking_pool, clock.unix_timestamp)?;
+        staking_pool.target_cap = target_cap;
+        staking_pool.apr_curve = apr_curve.clone();
+
+        emit!(AprCurveUpdatedEvent {
+            pool: staking_pool.key(),
+            target_cap,
+            apr_curve,
+            current_daily_apr: staking_pool.current_daily_apr(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Update the early withdrawal penalty (authority only)
+    pub fn update_penalty_config(
+        ctx: Context<UpdatePool>,
+        penalty_config: PenaltyConfig,
     ) -> Result<()> {
-        require!(amount >= MIN_STAKE_AMOUNT, StakingError::InsufficientAmount);
         require!(
-            (lock_period_days as i64 * SECONDS_PER_DAY) >= MIN_LOCK_PERIOD,
-            StakingError::LockPeriodTooShort
+            penalty_config.principal_penalty_bps as u64 <= MAX_PENALTY_BPS
+                && penalty_config.reward_forfeit_bps as u64 <= BASIS_POINTS,
+            StakingError::PenaltyTooHigh
         );
+
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        staking_pool.penalty_config = penalty_config;
+
+        emit!(PenaltyConfigUpdatedEvent {
+            pool: staking_pool.key(),
+            principal_penalty_bps: penalty_config.principal_penalty_bps,
+            reward_forfeit_bps: penalty_config.reward_forfeit_bps,
+            scale_with_remaining_lock: penalty_config.scale_with_remaining_lock,
+            destination: penalty_config.destination,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set the lock-duration reward multiplier curve (authority only)
+    pub fn set_lock_multipliers(
+        ctx: Context<UpdatePool>,
+        lock_multipliers: Vec<LockMultiplier>,
+    ) -> Result<()> {
         require!(
-            (lock_period_days as i64 * SECONDS_PER_DAY) <= MAX_LOCK_PERIOD,
-            StakingError::LockPeriodTooLong
+            lock_multipliers.len() <= MAX_MULTIPLIER_POINTS,
+            StakingError::TooManyMultiplierPoints
+        );
+        for (i, point) in lock_multipliers.iter().enumerate() {
+            require!(
+                point.multiplier_bps as u64 >= BASIS_POINTS && point.multiplier_bps <= MAX_MULTIPLIER_BPS,
+                StakingError::InvalidMultiplier
+            );
+            if i > 0 {
+                require!(
+                    point.lock_days > lock_multipliers[i - 1].lock_days,
+                    StakingError::InvalidMultiplierCurve
+                );
+            }
+        }
+
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        staking_pool.lock_multipliers = lock_multipliers.clone();
+
+        emit!(LockMultipliersUpdatedEvent {
+            pool: staking_pool.key(),
+            lock_multipliers,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set the pool capacity and per-wallet stake cap, zero meaning unlimited (authority only).
+    /// Liquid deposits are blocked while a per-wallet cap is set
+    pub fn set_stake_limits(
+        ctx: Context<UpdatePool>,
+        max_total_staked: u64,
+        max_stake_per_wallet: u64,
+    ) -> Result<()> {
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        staking_pool.max_total_staked = max_total_staked;
+        staking_pool.max_stake_per_wallet = max_stake_per_wallet;
+
+        emit!(StakeLimitsUpdatedEvent {
+            pool: staking_pool.key(),
+            max_total_staked,
+            max_stake_per_wallet,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Configure vesting of claimed rewards, zero period meaning rewards are paid out
+    /// directly. While vesting, rewards paid on exit are escrowed too and compounding
+    /// is disabled (authority only)
+    pub fn set_reward_vesting(
+        ctx: Context<UpdatePool>,
+        vesting_period: i64,
+        early_exit_penalty_bps: u16,
+    ) -> Result<()> {
+        require!(
+            (0..=MAX_LOCK_PERIOD_LIMIT).contains(&vesting_period),
+            StakingError::InvalidVestingConfig
         );
+        require!(early_exit_penalty_bps <= MAX_VESTING_PENALTY_BPS, StakingError::PenaltyTooHigh);
+
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        staking_pool.reward_vesting_period = vesting_period;
+        staking_pool.vesting_early_exit_bps = early_exit_penalty_bps;
+
+        emit!(RewardVestingUpdatedEvent {
+            pool: staking_pool.key(),
+            vesting_period,
+            early_exit_penalty_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
 
+    /// Add an extra reward mint paid alongside HELL (authority only)
+    pub fn add_reward_stream(ctx: Context<AddRewardStream>, reward_rate: u64) -> Result<()> {
         let clock = Clock::get()?;
-        let stake_account = &mut ctx.accounts.stake_account;
         let staking_pool = &mut ctx.accounts.staking_pool;
+        require!(
+            staking_pool.reward_streams.len() < MAX_REWARD_STREAMS,
+            StakingError::TooManyRewardStreams
+        );
+        require!(
+            staking_pool
+                .reward_streams
+                .iter()
+                .all(|stream| stream.mint != ctx.accounts.reward_mint.key()),
+            StakingError::RewardStreamExists
+        );
 
-        // Initialize stake account
-        stake_account.owner = ctx.accounts.user.key();
-        stake_account.amount = amount;
-        stake_account.start_time = clock.unix_timestamp;
-        stake_account.lock_period = lock_period_days as i64 * SECONDS_PER_DAY;
-        stake_account.last_claim_time = clock.unix_timestamp;
-        stake_account.total_claimed = 0;
-        stake_account.is_active = true;
-        stake_account.bump = ctx.bumps.stake_account;
+        update_reward_streams(staking_pool, clock.unix_timestamp);
+        staking_pool.reward_streams.push(RewardStream {
+            mint: ctx.accounts.reward_mint.key(),
+            vault: ctx.accounts.reward_vault.key(),
+            reward_rate,
+            acc_reward_per_share: 0,
+            last_update_time: clock.unix_timestamp,
+            total_funded: 0,
+            total_accrued: 0,
+            total_distributed: 0,
+        });
 
-        // Update staking pool
+        emit!(RewardStreamUpdatedEvent {
+            pool: staking_pool.key(),
+            index: (staking_pool.reward_streams.len() - 1) as u8,
+            mint: ctx.accounts.reward_mint.key(),
+            reward_rate,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Change the emission rate of a reward stream (authority only)
+    pub fn set_reward_stream_rate(
+        ctx: Context<UpdatePool>,
+        index: u8,
+        reward_rate: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        require!(
+            (index as usize) < staking_pool.reward_streams.len(),
+            StakingError::InvalidRewardStream
+        );
+
+        // Settle emissions at the old rate before switching
+        update_reward_streams(staking_pool, clock.unix_timestamp);
+        let stream = &mut staking_pool.reward_streams[index as usize];
+        stream.reward_rate = reward_rate;
+        let mint = stream.mint;
+
+        emit!(RewardStreamUpdatedEvent {
+            pool: staking_pool.key(),
+            index,
+            mint,
+            reward_rate,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Fund a reward stream vault (anyone can fund)
+    pub fn fund_reward_stream(ctx: Context<FundRewardStream>, index: u8, amount: u64) -> Result<()> {
+        require!(amount > 0, StakingError::InvalidAmount);
+
+        let clock = Clock::get()?;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        let stream = staking_pool
+            .reward_streams
+            .get_mut(index as usize)
+            .ok_or(StakingError::InvalidRewardStream)?;
+        require_keys_eq!(stream.vault, ctx.accounts.reward_vault.key(), StakingError::InvalidRewardStream);
+        stream.total_funded = stream.total_funded.checked_add(amount).unwrap();
+        let mint = stream.mint;
+
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.funder_token_account.to_account_info(),
+                to: ctx.accounts.reward_vault.to_account_info(),
+                authority: ctx.accounts.funder.to_account_info(),
+            },
+        );
+        token::transfer(transfer_ctx, amount)?;
+
+        emit!(RewardStreamFundedEvent {
+            pool: staking_pool.key(),
+            index,
+            mint,
+            funder: ctx.accounts.funder.key(),
+            amount,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Enable liquid staking and create the pool-share mint (authority only)
+    pub fn enable_liquid_mode(ctx: Context<EnableLiquidMode>, cooldown_period: i64) -> Result<()> {
+        require!(cooldown_period >= 0, StakingError::InvalidPoolConfig);
+
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        require!(!staking_pool.liquid.enabled, StakingError::LiquidModeAlreadyEnabled);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        accrue_reward_index(staking_pool, current_time);
+        staking_pool.liquid = LiquidState {
+            enabled: true,
+            share_mint: ctx.accounts.share_mint.key(),
+            cooldown_period,
+            last_update_time: current_time,
+            share_price: SHARE_PRICE_PRECISION as u64,
+            reward_index_snapshot: staking_pool.reward_index,
+            ..LiquidState::default()
+        };
+
+        Ok(())
+    }
+
+    /// Deposit tokens into liquid staking and receive pool shares
+    pub fn liquid_deposit(ctx: Context<LiquidDeposit>, amount: u64) -> Result<()> {
+        require!(ctx.accounts.staking_pool.is_operational(), StakingError::PoolPaused);
+        require!(amount > 0, StakingError::InvalidAmount);
+
+        let clock = Clock::get()?;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        require!(staking_pool.liquid.enabled, StakingError::LiquidModeDisabled);
+
+        // Pool shares are transferable, so a per-wallet cap cannot be enforced on them
+        require!(staking_pool.max_stake_per_wallet == 0, StakingError::LiquidDepositsCapped);
+
+        accrue_liquid_rewards(staking_pool, clock.unix_timestamp)?;
+        check_pool_capacity(staking_pool, amount)?;
+        let liquid = &staking_pool.liquid;
+        let shares = if liquid.total_shares == 0 || liquid.total_assets == 0 {
+            amount
+        } else {
+            (amount as u128 * liquid.total_shares as u128 / liquid.total_assets as u128) as u64
+        };
+        require!(shares > 0, StakingError::InvalidAmount);
+
+        staking_pool.liquid.total_assets = staking_pool.liquid.total_assets.checked_add(amount).unwrap();
+        staking_pool.liquid.total_shares = staking_pool.liquid.total_shares.checked_add(shares).unwrap();
         staking_pool.total_staked = staking_pool.total_staked.checked_add(amount).unwrap();
-        staking_pool.total_stakers = staking_pool.total_stakers.checked_add(1).unwrap();
+        update_share_price(staking_pool);
 
         // Transfer tokens from user to staking pool
         let transfer_ctx = CpiContext::new(
@@ -74,35 +519,117 @@ pub mod defla_staking {
         );
         token::transfer(transfer_ctx, amount)?;
 
-        emit!(StakeEvent {
+        // Mint pool shares to user
+        let seeds = &[
+            b"staking_pool",
+            ctx.accounts.defla_mint.to_account_info().key.as_ref(),
+            &[staking_pool.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let mint_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.share_mint.to_account_info(),
+                to: ctx.accounts.user_share_account.to_account_info(),
+                authority: staking_pool.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::mint_to(mint_ctx, shares)?;
+
+        ctx.accounts.pool_checkpoints.record(PoolCheckpoint {
+            timestamp: clock.unix_timestamp,
+            total_staked: staking_pool.total_staked,
+            total_weighted_stake: staking_pool.total_weighted_stake,
+        });
+
+        emit!(LiquidDepositEvent {
             user: ctx.accounts.user.key(),
+            pool: staking_pool.key(),
             amount,
-            lock_period: stake_account.lock_period,
+            shares,
+            share_price: staking_pool.liquid.share_price,
             timestamp: clock.unix_timestamp,
         });
 
         Ok(())
     }
 
-    /// Claim staking rewards
-    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
-        let stake_account = &mut ctx.accounts.stake_account;
+    /// Burn pool shares and queue the underlying tokens for withdrawal after the cooldown
+    pub fn request_liquid_redeem(
+        ctx: Context<RequestLiquidRedeem>,
+        ticket_id: u64,
+        shares: u64,
+    ) -> Result<()> {
+        require!(shares > 0, StakingError::InvalidAmount);
+
         let clock = Clock::get()?;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        require!(staking_pool.liquid.enabled, StakingError::LiquidModeDisabled);
+        require!(!staking_pool.emergency_mode, StakingError::EmergencyModeActive);
 
-        require!(stake_account.is_active, StakingError::StakeNotActive);
+        accrue_liquid_rewards(staking_pool, clock.unix_timestamp)?;
+        let amount = redeem_shares(staking_pool, shares)?;
+        staking_pool.liquid.pending_redemptions = staking_pool.liquid.pending_redemptions.checked_add(amount).unwrap();
 
-        let rewards = calculate_pending_rewards(stake_account, clock.unix_timestamp)?;
-        require!(rewards > 0, StakingError::NoRewardsToClaim);
+        // Burn the redeemed shares
+        let burn_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.share_mint.to_account_info(),
+                from: ctx.accounts.user_share_account.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        );
+        token::burn(burn_ctx, shares)?;
 
-        // Update stake account
-        stake_account.last_claim_time = clock.unix_timestamp;
-        stake_account.total_claimed = stake_account.total_claimed.checked_add(rewards).unwrap();
+        let ticket = &mut ctx.accounts.redemption_ticket;
+        ticket.owner = ctx.accounts.user.key();
+        ticket.pool = staking_pool.key();
+        ticket.ticket_id = ticket_id;
+        ticket.amount = amount;
+        ticket.unlock_time = clock.unix_timestamp.checked_add(staking_pool.liquid.cooldown_period).unwrap();
+        ticket.bump = ctx.bumps.redemption_ticket;
+
+        ctx.accounts.pool_checkpoints.record(PoolCheckpoint {
+            timestamp: clock.unix_timestamp,
+            total_staked: staking_pool.total_staked,
+            total_weighted_stake: staking_pool.total_weighted_stake,
+        });
+
+        emit!(LiquidRedeemRequestedEvent {
+            user: ctx.accounts.user.key(),
+            pool: staking_pool.key(),
+            ticket_id,
+            shares,
+            amount,
+            unlock_time: ticket.unlock_time,
+            share_price: staking_pool.liquid.share_price,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw the underlying tokens of a redemption ticket once its cooldown has passed.
+    /// The cooldown is waived in emergency mode, the amount having been settled at request
+    pub fn claim_liquid_redeem(ctx: Context<ClaimLiquidRedeem>) -> Result<()> {
+        let clock = Clock::get()?;
+        let ticket = &ctx.accounts.redemption_ticket;
+        require!(
+            ctx.accounts.staking_pool.emergency_mode || clock.unix_timestamp >= ticket.unlock_time,
+            StakingError::CooldownNotElapsed
+        );
+
+        let amount = ticket.amount;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        staking_pool.liquid.pending_redemptions = staking_pool.liquid.pending_redemptions.checked_sub(amount).unwrap();
 
-        // Transfer rewards from staking pool to user
         let seeds = &[
             b"staking_pool",
             ctx.accounts.defla_mint.to_account_info().key.as_ref(),
-            &[ctx.accounts.staking_pool.bump],
+            &[staking_pool.bump],
         ];
         let signer_seeds = &[&seeds[..]];
 
@@ -111,44 +638,46 @@ pub mod defla_staking {
             Transfer {
                 from: ctx.accounts.staking_pool_token_account.to_account_info(),
                 to: ctx.accounts.user_token_account.to_account_info(),
-                authority: ctx.accounts.staking_pool.to_account_info(),
+                authority: staking_pool.to_account_info(),
             },
             signer_seeds,
         );
-        token::transfer(transfer_ctx, rewards)?;
+        token::transfer(transfer_ctx, amount)?;
 
-        emit!(ClaimEvent {
+        emit!(LiquidRedeemClaimedEvent {
             user: ctx.accounts.user.key(),
-            amount: rewards,
+            pool: staking_pool.key(),
+            ticket_id: ticket.ticket_id,
+            amount,
             timestamp: clock.unix_timestamp,
         });
 
         Ok(())
     }
 
-    /// Unstake tokens (can be done before or after lock period)
-    pub fn unstake(ctx: Context<Unstake>) -> Result<()> {
-        let stake_account = &mut ctx.accounts.stake_account;
-        let staking_pool = &mut ctx.accounts.staking_pool;
-        let clock = Clock::get()?;
-
-        require!(stake_account.is_active, StakingError::StakeNotActive);
+    /// Burn pool shares and withdraw the underlying at once, at the share price settled
+    /// before emergency mode. Only available in emergency mode
+    pub fn emergency_liquid_redeem(ctx: Context<LiquidDeposit>, shares: u64) -> Result<()> {
+        require!(shares > 0, StakingError::InvalidAmount);
 
-        let is_early_withdrawal = clock.unix_timestamp < (stake_account.start_time + stake_account.lock_period);
-        
-        // Claim any pending rewards first
-        let pending_rewards = calculate_pending_rewards(stake_account, clock.unix_timestamp)?;
-        let total_to_transfer = stake_account.amount.checked_add(pending_rewards).unwrap();
+        let clock = Clock::get()?;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        require!(staking_pool.emergency_mode, StakingError::EmergencyModeDisabled);
 
-        // Update stake account
-        stake_account.is_active = false;
-        stake_account.total_claimed = stake_account.total_claimed.checked_add(pending_rewards).unwrap();
+        accrue_liquid_rewards(staking_pool, clock.unix_timestamp)?;
+        let amount = redeem_shares(staking_pool, shares)?;
 
-        // Update staking pool
-        staking_pool.total_staked = staking_pool.total_staked.checked_sub(stake_account.amount).unwrap();
-        staking_pool.total_stakers = staking_pool.total_stakers.checked_sub(1).unwrap();
+        // Burn the redeemed shares
+        let burn_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.share_mint.to_account_info(),
+                from: ctx.accounts.user_share_account.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        );
+        token::burn(burn_ctx, shares)?;
 
-        // Transfer tokens back to user
         let seeds = &[
             b"staking_pool",
             ctx.accounts.defla_mint.to_account_info().key.as_ref(),
@@ -165,85 +694,2394 @@ pub mod defla_staking {
             },
             signer_seeds,
         );
-        token::transfer(transfer_ctx, total_to_transfer)?;
+        token::transfer(transfer_ctx, amount)?;
 
-        emit!(UnstakeEvent {
+        ctx.accounts.pool_checkpoints.record(PoolCheckpoint {
+            timestamp: clock.unix_timestamp,
+            total_staked: staking_pool.total_staked,
+            total_weighted_stake: staking_pool.total_weighted_stake,
+        });
+
+        emit!(EmergencyLiquidRedeemEvent {
             user: ctx.accounts.user.key(),
-            amount: stake_account.amount,
-            rewards: pending_rewards,
-            early_withdrawal: is_early_withdrawal,
+            pool: staking_pool.key(),
+            shares,
+            amount,
+            share_price: staking_pool.liquid.share_price,
             timestamp: clock.unix_timestamp,
         });
 
         Ok(())
     }
-}
+
+    /// Stake HELL tokens
+    pub fn stake(
+        ctx: Context<Stake>,
+        amount: u64,
+        lock_period_days: u32,
+    ) -> Result<()> {
+        require!(ctx.accounts.staking_pool.is_operational(), StakingError::PoolPaused);
+        let config = ctx.accounts.staking_pool.config;
+        require!(amount >= config.min_stake_amount, StakingError::InsufficientAmount);
+        require!(
+            (lock_period_days as i64 * SECONDS_PER_DAY) >= config.min_lock_period,
+            StakingError::LockPeriodTooShort
+        );
+        require!(
+            (lock_period_days as i64 * SECONDS_PER_DAY) <= config.max_lock_period,
+            StakingError::LockPeriodTooLong
+        );
+
+        let clock = Clock::get()?;
+        let stake_account = &mut ctx.accounts.stake_account;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        check_stake_limits(staking_pool, 0, amount)?;
+
+        // Initialize stake account
+        stake_account.owner = ctx.accounts.user.key();
+        stake_account.amount = amount;
+        stake_account.start_time = clock.unix_timestamp;
+        stake_account.lock_period = lock_period_days as i64 * SECONDS_PER_DAY;
+        stake_account.multiplier_bps = lock_multiplier_bps(&staking_pool.lock_multipliers, lock_period_days);
+        stake_account.daily_apr = config.daily_apr;
+        stake_account.unbonding_period = config.unbonding_period;
+        stake_account.last_claim_time = clock.unix_timestamp;
+        stake_account.reward_index_snapshot = reward_index_at(staking_pool, clock.unix_timestamp);
+        stake_account.total_claimed = 0;
+        stake_account.is_active = true;
+        stake_account.stream_rewards = Vec::new();
+        stake_account.total_compounded = 0;
+        stake_account.auto_compound = false;
+        stake_account.compound_bounty_bps = 0;
+        stake_account.position_mint = None;
+        stake_account.unbonding_amount = 0;
+        stake_account.unbonding_principal = 0;
+        stake_account.unbonding_end = 0;
+        stake_account.bump = ctx.bumps.stake_account;
+
+        let voter_checkpoints = &mut ctx.accounts.voter_checkpoints;
+        if voter_checkpoints.owner == Pubkey::default() {
+            voter_checkpoints.owner = ctx.accounts.user.key();
+            voter_checkpoints.pool = staking_pool.key();
+            voter_checkpoints.bump = ctx.bumps.voter_checkpoints;
+        }
+
+        // Update staking pool
+        update_reward_streams(staking_pool, clock.unix_timestamp);
+        accrue_reward_index(staking_pool, clock.unix_timestamp);
+        staking_pool.total_staked = staking_pool.total_staked.checked_add(amount).unwrap();
+        staking_pool.total_stakers = staking_pool.total_stakers.checked_add(1).unwrap();
+        staking_pool.total_weighted_stake = staking_pool.total_weighted_stake.checked_add(weighted_amount(stake_account)).unwrap();
+        reset_stream_debts(staking_pool, stake_account);
+
+        // Transfer tokens from user to staking pool
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.user_token_account.to_account_info(),
+                to: ctx.accounts.staking_pool_token_account.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        );
+        token::transfer(transfer_ctx, amount)?;
+
+        record_checkpoints(
+            &mut ctx.accounts.voter_checkpoints,
+            &mut ctx.accounts.pool_checkpoints,
+            staking_pool,
+            stake_account,
+            clock.unix_timestamp,
+        );
+
+        emit!(StakeEvent {
+            user: ctx.accounts.user.key(),
+            amount,
+            lock_period: stake_account.lock_period,
+            multiplier_bps: stake_account.multiplier_bps,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Claim staking rewards. Extra reward streams are paid for every
+    /// (stream vault, user token account) pair passed in remaining accounts
+    pub fn claim_rewards<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimRewards<'info>>) -> Result<()> {
+        require!(ctx.accounts.staking_pool.is_operational(), StakingError::PoolPaused);
+        authorize_position(
+            &ctx.accounts.stake_account,
+            &ctx.accounts.user.key(),
+            ctx.accounts.position_token_account.as_ref(),
+        )?;
+
+        let stake_account = &mut ctx.accounts.stake_account;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        let clock = Clock::get()?;
+
+        require!(stake_account.is_active, StakingError::StakeNotActive);
+
+        let rewards = calculate_pending_rewards(stake_account, staking_pool, clock.unix_timestamp)?;
+
+        // Settle extra reward streams
+        update_reward_streams(staking_pool, clock.unix_timestamp);
+        settle_stream_rewards(staking_pool, stake_account);
+        let streams_paid = pay_stream_rewards(
+            staking_pool,
+            stake_account,
+            ctx.accounts.user.key(),
+            ctx.remaining_accounts,
+            &ctx.accounts.token_program,
+            false,
+        )?;
+        require!(rewards > 0 || streams_paid, StakingError::NoRewardsToClaim);
+
+        // Update stake account
+        stake_account.last_claim_time = clock.unix_timestamp;
+        stake_account.reward_index_snapshot = reward_index_at(staking_pool, clock.unix_timestamp);
+        stake_account.total_claimed = stake_account.total_claimed.checked_add(rewards).unwrap();
+
+        // Move rewards into the vesting escrow when the pool vests claims
+        if rewards > 0 && staking_pool.reward_vesting_period > 0 {
+            escrow_rewards(
+                staking_pool,
+                ctx.accounts.reward_escrow.as_deref_mut(),
+                ctx.bumps.reward_escrow,
+                ctx.accounts.user.key(),
+                rewards,
+                clock.unix_timestamp,
+            )?;
+        } else if rewards > 0 {
+            // Transfer rewards from staking pool to user
+            let seeds = &[
+                b"staking_pool",
+                ctx.accounts.defla_mint.to_account_info().key.as_ref(),
+                &[staking_pool.bump],
+            ];
+            let signer_seeds = &[&seeds[..]];
+
+            let transfer_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.staking_pool_token_account.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                    authority: staking_pool.to_account_info(),
+                },
+                signer_seeds,
+            );
+            token::transfer(transfer_ctx, rewards)?;
+        }
+
+        let daily_apr = if staking_pool.dynamic_apr_enabled() {
+            staking_pool.current_daily_apr()
+        } else {
+            stake_account.daily_apr
+        };
+
+        emit!(ClaimEvent {
+            user: ctx.accounts.user.key(),
+            amount: rewards,
+            daily_apr,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw vested rewards from the escrow. With `early_exit` the unvested balance
+    /// is released as well, minus a penalty that is burned
+    pub fn withdraw_vested_rewards(ctx: Context<WithdrawVestedRewards>, early_exit: bool) -> Result<()> {
+        require!(ctx.accounts.staking_pool.is_operational(), StakingError::PoolPaused);
+
+        let clock = Clock::get()?;
+        let reward_escrow = &mut ctx.accounts.reward_escrow;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+
+        reward_escrow.release(clock.unix_timestamp);
+        let mut payout = reward_escrow.vested_amount;
+        let mut penalty = 0;
+        if early_exit && reward_escrow.locked_amount > 0 {
+            let unvested = reward_escrow.locked_amount;
+            penalty = (unvested as u128 * staking_pool.vesting_early_exit_bps as u128 / BASIS_POINTS as u128) as u64;
+            payout = payout.checked_add(unvested - penalty).unwrap();
+            reward_escrow.locked_amount = 0;
+            reward_escrow.vesting_start = clock.unix_timestamp;
+            reward_escrow.vesting_end = clock.unix_timestamp;
+        }
+        require!(payout > 0 || penalty > 0, StakingError::NoRewardsToClaim);
+
+        reward_escrow.vested_amount = 0;
+        reward_escrow.total_withdrawn = reward_escrow.total_withdrawn.checked_add(payout).unwrap();
+        reward_escrow.total_penalized = reward_escrow.total_penalized.checked_add(penalty).unwrap();
+        staking_pool.total_escrowed_rewards = staking_pool
+            .total_escrowed_rewards
+            .checked_sub(payout.checked_add(penalty).unwrap())
+            .unwrap();
+
+        let seeds = &[
+            b"staking_pool",
+            ctx.accounts.defla_mint.to_account_info().key.as_ref(),
+            &[staking_pool.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        if penalty > 0 {
+            let burn_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.defla_mint.to_account_info(),
+                    from: ctx.accounts.staking_pool_token_account.to_account_info(),
+                    authority: staking_pool.to_account_info(),
+                },
+                signer_seeds,
+            );
+            token::burn(burn_ctx, penalty)?;
+            staking_pool.total_penalties_burned = staking_pool.total_penalties_burned.checked_add(penalty).unwrap();
+        }
+
+        if payout > 0 {
+            let transfer_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.staking_pool_token_account.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                    authority: staking_pool.to_account_info(),
+                },
+                signer_seeds,
+            );
+            token::transfer(transfer_ctx, payout)?;
+        }
+
+        emit!(VestedRewardsWithdrawnEvent {
+            user: ctx.accounts.user.key(),
+            pool: staking_pool.key(),
+            amount: payout,
+            penalty,
+            remaining_locked: reward_escrow.locked_amount,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Represent the position as a one-of-one NFT. From then on, whoever holds
+    /// the NFT controls the position instead of the original owner
+    pub fn tokenize_position(
+        ctx: Context<TokenizePosition>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        require!(name.len() <= 32, StakingError::NameTooLong);
+        require!(symbol.len() <= 10, StakingError::SymbolTooLong);
+        require!(uri.len() <= 200, StakingError::UriTooLong);
+
+        let staking_pool = &ctx.accounts.staking_pool;
+        let seeds = &[
+            b"staking_pool",
+            staking_pool.defla_mint.as_ref(),
+            &[staking_pool.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        // Mint the single position token to the owner
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.position_mint.to_account_info(),
+                    to: ctx.accounts.user_position_account.to_account_info(),
+                    authority: staking_pool.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            1,
+        )?;
+
+        let data_v2 = DataV2 {
+            name,
+            symbol,
+            uri,
+            seller_fee_basis_points: 0,
+            creators: None,
+            collection: None,
+            uses: None,
+        };
+
+        create_metadata_accounts_v3(
+            CpiContext::new_with_signer(
+                ctx.accounts.metadata_program.to_account_info(),
+                CreateMetadataAccountsV3 {
+                    metadata: ctx.accounts.metadata.to_account_info(),
+                    mint: ctx.accounts.position_mint.to_account_info(),
+                    mint_authority: staking_pool.to_account_info(),
+                    update_authority: staking_pool.to_account_info(),
+                    payer: ctx.accounts.user.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            data_v2,
+            true, // is_mutable
+            true, // update_authority_is_signer
+            None, // collection_details
+        )?;
+
+        // Fix the supply at one
+        token::set_authority(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                SetAuthority {
+                    current_authority: staking_pool.to_account_info(),
+                    account_or_mint: ctx.accounts.position_mint.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            AuthorityType::MintTokens,
+            None,
+        )?;
+
+        let clock = Clock::get()?;
+        let stake_account = &mut ctx.accounts.stake_account;
+        stake_account.position_mint = Some(ctx.accounts.position_mint.key());
+
+        // The position's voting power ends once it is transferable
+        record_checkpoints(
+            &mut ctx.accounts.voter_checkpoints,
+            &mut ctx.accounts.pool_checkpoints,
+            staking_pool,
+            stake_account,
+            clock.unix_timestamp,
+        );
+
+        emit!(PositionTokenizedEvent {
+            owner: ctx.accounts.user.key(),
+            pool: staking_pool.key(),
+            stake_account: stake_account.key(),
+            position_mint: ctx.accounts.position_mint.key(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Compound pending rewards into the staked principal
+    pub fn compound(ctx: Context<Compound>) -> Result<()> {
+        require!(ctx.accounts.staking_pool.is_operational(), StakingError::PoolPaused);
+        authorize_position(
+            &ctx.accounts.stake_account,
+            &ctx.accounts.user.key(),
+            ctx.accounts.position_token_account.as_ref(),
+        )?;
+
+        let clock = Clock::get()?;
+        let stake_account = &mut ctx.accounts.stake_account;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+
+        require!(stake_account.is_active, StakingError::StakeNotActive);
+        require!(staking_pool.reward_vesting_period == 0, StakingError::CompoundingWhileVesting);
+
+        let rewards = calculate_pending_rewards(stake_account, staking_pool, clock.unix_timestamp)?;
+        require!(rewards > 0, StakingError::NoRewardsToClaim);
+
+        compound_position(staking_pool, stake_account, rewards, clock.unix_timestamp)?;
+
+        record_checkpoints(
+            &mut ctx.accounts.voter_checkpoints,
+            &mut ctx.accounts.pool_checkpoints,
+            staking_pool,
+            stake_account,
+            clock.unix_timestamp,
+        );
+
+        emit!(CompoundEvent {
+            user: stake_account.owner,
+            pool: staking_pool.key(),
+            amount_compounded: rewards,
+            bounty: 0,
+            cranker: None,
+            new_amount: stake_account.amount,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Opt in or out of permissionless compounding in exchange for a bounty
+    pub fn set_auto_compound(
+        ctx: Context<SetAutoCompound>,
+        enabled: bool,
+        bounty_bps: u16,
+    ) -> Result<()> {
+        require!(bounty_bps <= MAX_COMPOUND_BOUNTY_BPS, StakingError::BountyTooHigh);
+        authorize_position(
+            &ctx.accounts.stake_account,
+            &ctx.accounts.user.key(),
+            ctx.accounts.position_token_account.as_ref(),
+        )?;
+
+        let stake_account = &mut ctx.accounts.stake_account;
+        stake_account.auto_compound = enabled;
+        stake_account.compound_bounty_bps = bounty_bps;
+
+        Ok(())
+    }
+
+    /// Compound an opted-in position on behalf of its owner (anyone can crank).
+    /// The cranker receives the position's bounty share of the rewards
+    pub fn crank_compound(ctx: Context<CrankCompound>) -> Result<()> {
+        require!(ctx.accounts.staking_pool.is_operational(), StakingError::PoolPaused);
+        let clock = Clock::get()?;
+        let stake_account = &mut ctx.accounts.stake_account;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+
+        require!(stake_account.is_active, StakingError::StakeNotActive);
+        require!(staking_pool.reward_vesting_period == 0, StakingError::CompoundingWhileVesting);
+        require!(stake_account.auto_compound, StakingError::AutoCompoundDisabled);
+
+        let rewards = calculate_pending_rewards(stake_account, staking_pool, clock.unix_timestamp)?;
+        require!(rewards > 0, StakingError::NoRewardsToClaim);
+
+        let bounty = (rewards as u128 * stake_account.compound_bounty_bps as u128 / BASIS_POINTS as u128) as u64;
+        let amount_compounded = rewards.checked_sub(bounty).unwrap();
+
+        compound_position(staking_pool, stake_account, amount_compounded, clock.unix_timestamp)?;
+
+        // Pay the bounty from the reward vault
+        if bounty > 0 {
+            let seeds = &[
+                b"staking_pool",
+                ctx.accounts.defla_mint.to_account_info().key.as_ref(),
+                &[staking_pool.bump],
+            ];
+            let signer_seeds = &[&seeds[..]];
+
+            let transfer_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.staking_pool_token_account.to_account_info(),
+                    to: ctx.accounts.cranker_token_account.to_account_info(),
+                    authority: staking_pool.to_account_info(),
+                },
+                signer_seeds,
+            );
+            token::transfer(transfer_ctx, bounty)?;
+        }
+
+        record_checkpoints(
+            &mut ctx.accounts.voter_checkpoints,
+            &mut ctx.accounts.pool_checkpoints,
+            staking_pool,
+            stake_account,
+            clock.unix_timestamp,
+        );
+
+        emit!(CompoundEvent {
+            user: stake_account.owner,
+            pool: staking_pool.key(),
+            amount_compounded,
+            bounty,
+            cranker: Some(ctx.accounts.cranker.key()),
+            new_amount: stake_account.amount,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Unstake tokens (can be done before or after lock period). Accrued extra
+    /// stream rewards must be paid out through remaining accounts, as in `claim_rewards`
+    pub fn unstake<'info>(ctx: Context<'_, '_, 'info, 'info, Unstake<'info>>) -> Result<()> {
+        authorize_position(
+            &ctx.accounts.stake_account,
+            &ctx.accounts.user.key(),
+            ctx.accounts.position_token_account.as_ref(),
+        )?;
+        require!(
+            ctx.accounts.stake_account.unbonding_period == 0,
+            StakingError::UnbondingRequired
+        );
+        require!(!ctx.accounts.staking_pool.emergency_mode, StakingError::EmergencyModeActive);
+
+        let stake_account = &mut ctx.accounts.stake_account;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        let clock = Clock::get()?;
+
+        require!(stake_account.is_active, StakingError::StakeNotActive);
+
+        let mut settlement = settle_exit(
+            staking_pool,
+            stake_account,
+            ctx.accounts.user.key(),
+            ctx.remaining_accounts,
+            &ctx.accounts.token_program,
+            clock.unix_timestamp,
+        )?;
+
+        escrow_exit_rewards(
+            staking_pool,
+            &mut settlement,
+            ctx.accounts.reward_escrow.as_deref_mut(),
+            ctx.bumps.reward_escrow,
+            ctx.accounts.user.key(),
+            clock.unix_timestamp,
+        )?;
+
+        // Transfer tokens back to user
+        let seeds = &[
+            b"staking_pool",
+            ctx.accounts.defla_mint.to_account_info().key.as_ref(),
+            &[staking_pool.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.staking_pool_token_account.to_account_info(),
+                to: ctx.accounts.user_token_account.to_account_info(),
+                authority: staking_pool.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::transfer(transfer_ctx, settlement.payout())?;
+
+        retire_position_nft(
+            stake_account,
+            ctx.accounts.position_mint.as_ref(),
+            ctx.accounts.position_token_account.as_ref(),
+            &ctx.accounts.user,
+            &ctx.accounts.token_program,
+        )?;
+        apply_penalty(
+            staking_pool,
+            &ctx.accounts.defla_mint,
+            &ctx.accounts.staking_pool_token_account,
+            &ctx.accounts.token_program,
+            settlement.total_penalty(),
+        )?;
+
+        record_checkpoints(
+            &mut ctx.accounts.voter_checkpoints,
+            &mut ctx.accounts.pool_checkpoints,
+            staking_pool,
+            stake_account,
+            clock.unix_timestamp,
+        );
+
+        emit!(UnstakeEvent {
+            user: ctx.accounts.user.key(),
+            amount: settlement.principal,
+            rewards: settlement.rewards_paid,
+            early_withdrawal: settlement.early_withdrawal,
+            principal_penalty: settlement.principal_penalty,
+            rewards_forfeited: settlement.rewards_forfeited,
+            penalty_destination: staking_pool.penalty_config.destination,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw part of a position. Rewards are settled, the lock schedule is kept
+    /// for the remainder, and withdrawing everything closes the position
+    pub fn unstake_partial<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UnstakePartial<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        authorize_position(
+            &ctx.accounts.stake_account,
+            &ctx.accounts.user.key(),
+            ctx.accounts.position_token_account.as_ref(),
+        )?;
+        require!(
+            ctx.accounts.stake_account.unbonding_period == 0,
+            StakingError::UnbondingRequired
+        );
+        require!(!ctx.accounts.staking_pool.emergency_mode, StakingError::EmergencyModeActive);
+
+        let stake_account = &mut ctx.accounts.stake_account;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        let clock = Clock::get()?;
+
+        require!(stake_account.is_active, StakingError::StakeNotActive);
+        require!(amount > 0 && amount <= stake_account.amount, StakingError::InvalidAmount);
+
+        let remaining = stake_account.amount - amount;
+        require!(
+            remaining == 0 || remaining >= staking_pool.config.min_stake_amount,
+            StakingError::RemainderBelowMinimum
+        );
+
+        let mut settlement = if remaining == 0 {
+            settle_exit(
+                staking_pool,
+                stake_account,
+                ctx.accounts.user.key(),
+                ctx.remaining_accounts,
+                &ctx.accounts.token_program,
+                clock.unix_timestamp,
+            )?
+        } else {
+            let early_withdrawal = clock.unix_timestamp < (stake_account.start_time + stake_account.lock_period);
+            let pending_rewards = calculate_pending_rewards(stake_account, staking_pool, clock.unix_timestamp)?;
+
+            // Penalize only the withdrawn share of principal and rewards
+            let (principal_penalty, rewards_forfeited) = if early_withdrawal {
+                let withdrawn_rewards = (pending_rewards as u128 * amount as u128 / stake_account.amount as u128) as u64;
+                calculate_early_withdrawal_penalty(
+                    &staking_pool.penalty_config,
+                    stake_account,
+                    amount,
+                    withdrawn_rewards,
+                    clock.unix_timestamp,
+                )
+            } else {
+                (0, 0)
+            };
+            let rewards_paid = pending_rewards.checked_sub(rewards_forfeited).unwrap();
+
+            // Settle extra reward streams before the position's weight changes
+            update_reward_streams(staking_pool, clock.unix_timestamp);
+            settle_stream_rewards(staking_pool, stake_account);
+            pay_stream_rewards(
+                staking_pool,
+                stake_account,
+                ctx.accounts.user.key(),
+                ctx.remaining_accounts,
+                &ctx.accounts.token_program,
+                false,
+            )?;
+            accrue_reward_index(staking_pool, clock.unix_timestamp);
+            let old_weight = weighted_amount(stake_account);
+
+            // Update stake account
+            stake_account.amount = remaining;
+            stake_account.last_claim_time = clock.unix_timestamp;
+            stake_account.reward_index_snapshot = reward_index_at(staking_pool, clock.unix_timestamp);
+            stake_account.total_claimed = stake_account.total_claimed.checked_add(rewards_paid).unwrap();
+
+            // Update staking pool
+            staking_pool.total_staked = staking_pool.total_staked.checked_sub(amount).unwrap();
+            staking_pool.total_weighted_stake = staking_pool
+                .total_weighted_stake
+                .checked_sub(old_weight)
+                .unwrap()
+                .checked_add(weighted_amount(stake_account))
+                .unwrap();
+            reset_stream_debts(staking_pool, stake_account);
+
+            ExitSettlement {
+                principal: amount,
+                rewards_paid,
+                rewards_escrowed: 0,
+                principal_penalty,
+                rewards_forfeited,
+                early_withdrawal,
+            }
+        };
+
+        escrow_exit_rewards(
+            staking_pool,
+            &mut settlement,
+            ctx.accounts.reward_escrow.as_deref_mut(),
+            ctx.bumps.reward_escrow,
+            ctx.accounts.user.key(),
+            clock.unix_timestamp,
+        )?;
+
+        // Transfer tokens back to user
+        let seeds = &[
+            b"staking_pool",
+            ctx.accounts.defla_mint.to_account_info().key.as_ref(),
+            &[staking_pool.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.staking_pool_token_account.to_account_info(),
+                to: ctx.accounts.user_token_account.to_account_info(),
+                authority: staking_pool.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::transfer(transfer_ctx, settlement.payout())?;
+
+        apply_penalty(
+            staking_pool,
+            &ctx.accounts.defla_mint,
+            &ctx.accounts.staking_pool_token_account,
+            &ctx.accounts.token_program,
+            settlement.total_penalty(),
+        )?;
+
+        record_checkpoints(
+            &mut ctx.accounts.voter_checkpoints,
+            &mut ctx.accounts.pool_checkpoints,
+            staking_pool,
+            stake_account,
+            clock.unix_timestamp,
+        );
+
+        emit!(PartialUnstakeEvent {
+            user: ctx.accounts.user.key(),
+            pool: staking_pool.key(),
+            amount,
+            remaining,
+            rewards: settlement.rewards_paid,
+            early_withdrawal: settlement.early_withdrawal,
+            principal_penalty: settlement.principal_penalty,
+            rewards_forfeited: settlement.rewards_forfeited,
+            timestamp: clock.unix_timestamp,
+        });
+
+        // Withdrawing everything closes the position
+        if remaining == 0 {
+            retire_position_nft(
+                &ctx.accounts.stake_account,
+                ctx.accounts.position_mint.as_ref(),
+                ctx.accounts.position_token_account.as_ref(),
+                &ctx.accounts.user,
+                &ctx.accounts.token_program,
+            )?;
+            ctx.accounts.stake_account.close(ctx.accounts.user.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    /// Start unbonding a position. Rewards stop accruing and the payout is
+    /// released by `withdraw_unstaked` once the pool's unbonding period has passed
+    pub fn request_unstake<'info>(ctx: Context<'_, '_, 'info, 'info, RequestUnstake<'info>>) -> Result<()> {
+        authorize_position(
+            &ctx.accounts.stake_account,
+            &ctx.accounts.user.key(),
+            ctx.accounts.position_token_account.as_ref(),
+        )?;
+        require!(!ctx.accounts.staking_pool.emergency_mode, StakingError::EmergencyModeActive);
+
+        let stake_account = &mut ctx.accounts.stake_account;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        let clock = Clock::get()?;
+
+        require!(stake_account.is_active, StakingError::StakeNotActive);
+
+        let mut settlement = settle_exit(
+            staking_pool,
+            stake_account,
+            ctx.accounts.user.key(),
+            ctx.remaining_accounts,
+            &ctx.accounts.token_program,
+            clock.unix_timestamp,
+        )?;
+        escrow_exit_rewards(
+            staking_pool,
+            &mut settlement,
+            ctx.accounts.reward_escrow.as_deref_mut(),
+            ctx.bumps.reward_escrow,
+            ctx.accounts.user.key(),
+            clock.unix_timestamp,
+        )?;
+        apply_penalty(
+            staking_pool,
+            &ctx.accounts.defla_mint,
+            &ctx.accounts.staking_pool_token_account,
+            &ctx.accounts.token_program,
+            settlement.total_penalty(),
+        )?;
+
+        let payout = settlement.payout();
+        stake_account.unbonding_amount = payout;
+        stake_account.unbonding_principal = settlement.principal.checked_sub(settlement.principal_penalty).unwrap();
+        stake_account.unbonding_end = clock.unix_timestamp.checked_add(stake_account.unbonding_period).unwrap();
+
+        staking_pool.total_unbonding = staking_pool.total_unbonding.checked_add(payout).unwrap();
+        staking_pool.pending_unbondings = staking_pool.pending_unbondings.checked_add(1).unwrap();
+
+        record_checkpoints(
+            &mut ctx.accounts.voter_checkpoints,
+            &mut ctx.accounts.pool_checkpoints,
+            staking_pool,
+            stake_account,
+            clock.unix_timestamp,
+        );
+
+        emit!(UnbondingRequestedEvent {
+            user: ctx.accounts.user.key(),
+            pool: staking_pool.key(),
+            amount: settlement.principal,
+            rewards: settlement.rewards_paid,
+            early_withdrawal: settlement.early_withdrawal,
+            principal_penalty: settlement.principal_penalty,
+            rewards_forfeited: settlement.rewards_forfeited,
+            unbonding_end: stake_account.unbonding_end,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw principal only, ignoring locks and cooldowns. Only available in
+    /// emergency mode; pending rewards of every kind are forfeited
+    pub fn emergency_withdraw<'info>(ctx: Context<'_, '_, 'info, 'info, Unstake<'info>>) -> Result<()> {
+        require!(ctx.accounts.staking_pool.emergency_mode, StakingError::EmergencyModeDisabled);
+        authorize_position(
+            &ctx.accounts.stake_account,
+            &ctx.accounts.user.key(),
+            ctx.accounts.position_token_account.as_ref(),
+        )?;
+
+        let stake_account = &mut ctx.accounts.stake_account;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        let clock = Clock::get()?;
+
+        let amount = if stake_account.is_active {
+            // Stream rewards accrued so far stay in their vaults
+            update_reward_streams(staking_pool, clock.unix_timestamp);
+            accrue_reward_index(staking_pool, clock.unix_timestamp);
+            staking_pool.total_weighted_stake = staking_pool.total_weighted_stake.checked_sub(weighted_amount(stake_account)).unwrap();
+            staking_pool.total_staked = staking_pool.total_staked.checked_sub(stake_account.amount).unwrap();
+            staking_pool.total_stakers = staking_pool.total_stakers.checked_sub(1).unwrap();
+            stake_account.is_active = false;
+            stake_account.amount
+        } else {
+            // Rewards included in the unbonding amount stay in the vault
+            require!(stake_account.unbonding_end > 0, StakingError::StakeNotActive);
+            staking_pool.total_unbonding = staking_pool.total_unbonding.checked_sub(stake_account.unbonding_amount).unwrap();
+            staking_pool.pending_unbondings = staking_pool.pending_unbondings.checked_sub(1).unwrap();
+            stake_account.unbonding_principal
+        };
+
+        let seeds = &[
+            b"staking_pool",
+            ctx.accounts.defla_mint.to_account_info().key.as_ref(),
+            &[staking_pool.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.staking_pool_token_account.to_account_info(),
+                to: ctx.accounts.user_token_account.to_account_info(),
+                authority: staking_pool.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::transfer(transfer_ctx, amount)?;
+
+        retire_position_nft(
+            stake_account,
+            ctx.accounts.position_mint.as_ref(),
+            ctx.accounts.position_token_account.as_ref(),
+            &ctx.accounts.user,
+            &ctx.accounts.token_program,
+        )?;
+
+        record_checkpoints(
+            &mut ctx.accounts.voter_checkpoints,
+            &mut ctx.accounts.pool_checkpoints,
+            staking_pool,
+            stake_account,
+            clock.unix_timestamp,
+        );
+
+        emit!(EmergencyWithdrawEvent {
+            user: ctx.accounts.user.key(),
+            pool: staking_pool.key(),
+            amount,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Return a wallet's lock-weighted voting power at `timestamp`. Power decays
+    /// linearly as the lock approaches its end. Tokenized positions carry no votes
+    pub fn get_voting_power(ctx: Context<GetVotingPower>, timestamp: i64) -> Result<u64> {
+        let checkpoint = ctx.accounts.voter_checkpoints.at(timestamp)?;
+        Ok(checkpoint.map_or(0, |checkpoint| voting_power(&checkpoint, timestamp)))
+    }
+
+    /// Return the pool-wide stake at `timestamp`, e.g. as the quorum base for a vote.
+    /// All zeros before the pool's first checkpoint
+    pub fn get_pool_stake(ctx: Context<GetPoolStake>, timestamp: i64) -> Result<PoolCheckpoint> {
+        Ok(ctx.accounts.pool_checkpoints.at(timestamp)?.unwrap_or_default())
+    }
+
+    /// Release an unbonded position once its cooldown has passed
+    pub fn withdraw_unstaked(ctx: Context<WithdrawUnstaked>) -> Result<()> {
+        authorize_position(
+            &ctx.accounts.stake_account,
+            &ctx.accounts.user.key(),
+            ctx.accounts.position_token_account.as_ref(),
+        )?;
+        require!(!ctx.accounts.staking_pool.emergency_mode, StakingError::EmergencyModeActive);
+
+        let stake_account = &mut ctx.accounts.stake_account;
+        let staking_pool = &mut ctx.accounts.staking_pool;
+        let clock = Clock::get()?;
+
+        require!(stake_account.unbonding_end > 0, StakingError::NotUnbonding);
+        require!(clock.unix_timestamp >= stake_account.unbonding_end, StakingError::CooldownNotElapsed);
+
+        let amount = stake_account.unbonding_amount;
+        staking_pool.total_unbonding = staking_pool.total_unbonding.checked_sub(amount).unwrap();
+        staking_pool.pending_unbondings = staking_pool.pending_unbondings.checked_sub(1).unwrap();
+
+        let seeds = &[
+            b"staking_pool",
+            ctx.accounts.defla_mint.to_account_info().key.as_ref(),
+            &[staking_pool.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.staking_pool_token_account.to_account_info(),
+                to: ctx.accounts.user_token_account.to_account_info(),
+                authority: staking_pool.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::transfer(transfer_ctx, amount)?;
+
+        retire_position_nft(
+            stake_account,
+            ctx.accounts.position_mint.as_ref(),
+            ctx.accounts.position_token_account.as_ref(),
+            &ctx.accounts.user,
+            &ctx.accounts.token_program,
+        )?;
+
+        emit!(UnbondingWithdrawnEvent {
+            user: ctx.accounts.user.key(),
+            pool: staking_pool.key(),
+            amount,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+// Amounts owed when a position exits
+struct ExitSettlement {
+    principal: u64,
+    rewards_paid: u64,
+    rewards_escrowed: u64, // part of rewards_paid sent to the vesting escrow
+    principal_penalty: u64,
+    rewards_forfeited: u64,
+    early_withdrawal: bool,
+}
+
+impl ExitSettlement {
+    fn payout(&self) -> u64 {
+        self.principal
+            .checked_sub(self.principal_penalty)
+            .unwrap()
+            .checked_add(self.rewards_paid - self.rewards_escrowed)
+            .unwrap()
+    }
+
+    fn total_penalty(&self) -> u64 {
+        self.principal_penalty.checked_add(self.rewards_forfeited).unwrap()
+    }
+}
+
+// Helper function to close out a position's accounting: settles rewards and the early
+// withdrawal penalty, pays extra reward streams and removes the stake from the pool.
+// Token movements of the principal and penalty are left to the caller
+fn settle_exit<'info>(
+    staking_pool: &mut Account<'info, StakingPool>,
+    stake_account: &mut StakeAccount,
+    recipient: Pubkey,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    token_program: &Program<'info, Token>,
+    current_time: i64,
+) -> Result<ExitSettlement> {
+    let early_withdrawal = current_time < (stake_account.start_time + stake_account.lock_period);
+
+    // Claim any pending rewards first
+    let pending_rewards = calculate_pending_rewards(stake_account, staking_pool, current_time)?;
+
+    // Apply the early withdrawal penalty, if any
+    let (principal_penalty, rewards_forfeited) = if early_withdrawal {
+        calculate_early_withdrawal_penalty(
+            &staking_pool.penalty_config,
+            stake_account,
+            stake_account.amount,
+            pending_rewards,
+            current_time,
+        )
+    } else {
+        (0, 0)
+    };
+    let rewards_paid = pending_rewards.checked_sub(rewards_forfeited).unwrap();
+
+    // Pay out extra reward streams before the position is closed
+    update_reward_streams(staking_pool, current_time);
+    settle_stream_rewards(staking_pool, stake_account);
+    pay_stream_rewards(
+        staking_pool,
+        stake_account,
+        recipient,
+        remaining_accounts,
+        token_program,
+        true,
+    )?;
+    staking_pool.total_weighted_stake = staking_pool.total_weighted_stake.checked_sub(weighted_amount(stake_account)).unwrap();
+    accrue_reward_index(staking_pool, current_time);
+
+    // Update stake account
+    stake_account.is_active = false;
+    stake_account.total_claimed = stake_account.total_claimed.checked_add(rewards_paid).unwrap();
+
+    // Update staking pool
+    staking_pool.total_staked = staking_pool.total_staked.checked_sub(stake_account.amount).unwrap();
+    staking_pool.total_stakers = staking_pool.total_stakers.checked_sub(1).unwrap();
+
+    Ok(ExitSettlement {
+        principal: stake_account.amount,
+        rewards_paid,
+        rewards_escrowed: 0,
+        principal_penalty,
+        rewards_forfeited,
+        early_withdrawal,
+    })
+}
+
+// Helper function to vest the rewards of an exit like claims do, when the pool vests.
+// Escrowed rewards are left out of the settlement payout
+fn escrow_exit_rewards(
+    staking_pool: &mut Account<'_, StakingPool>,
+    settlement: &mut ExitSettlement,
+    reward_escrow: Option<&mut RewardEscrow>,
+    escrow_bump: Option<u8>,
+    owner: Pubkey,
+    current_time: i64,
+) -> Result<()> {
+    if staking_pool.reward_vesting_period == 0 || settlement.rewards_paid == 0 {
+        return Ok(());
+    }
+
+    escrow_rewards(staking_pool, reward_escrow, escrow_bump, owner, settlement.rewards_paid, current_time)?;
+    settlement.rewards_escrowed = settlement.rewards_paid;
+    Ok(())
+}
+
+// Helper function to lock claimed rewards in the owner's vesting escrow. Topping up
+// restarts the vesting window for everything still locked.
+fn escrow_rewards(
+    staking_pool: &mut Account<'_, StakingPool>,
+    reward_escrow: Option<&mut RewardEscrow>,
+    escrow_bump: Option<u8>,
+    owner: Pubkey,
+    amount: u64,
+    current_time: i64,
+) -> Result<()> {
+    let reward_escrow = reward_escrow.ok_or(StakingError::RewardEscrowRequired)?;
+    if reward_escrow.owner == Pubkey::default() {
+        reward_escrow.owner = owner;
+        reward_escrow.pool = staking_pool.key();
+        reward_escrow.bump = escrow_bump.unwrap();
+    }
+    reward_escrow.release(current_time);
+    reward_escrow.locked_amount = reward_escrow.locked_amount.checked_add(amount).unwrap();
+    reward_escrow.vesting_start = current_time;
+    reward_escrow.vesting_end = current_time + staking_pool.reward_vesting_period;
+    staking_pool.total_escrowed_rewards = staking_pool.total_escrowed_rewards.checked_add(amount).unwrap();
+
+    emit!(RewardsEscrowedEvent {
+        user: owner,
+        pool: staking_pool.key(),
+        amount,
+        locked_amount: reward_escrow.locked_amount,
+        vesting_end: reward_escrow.vesting_end,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+// Helper function to burn the penalty or leave it in the vault to fund remaining stakers
+fn apply_penalty<'info>(
+    staking_pool: &mut Account<'info, StakingPool>,
+    defla_mint: &Account<'info, Mint>,
+    staking_pool_token_account: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    total_penalty: u64,
+) -> Result<()> {
+    if total_penalty == 0 {
+        return Ok(());
+    }
+
+    match staking_pool.penalty_config.destination {
+        PenaltyDestination::Burn => {
+            let seeds = &[
+                b"staking_pool",
+                defla_mint.to_account_info().key.as_ref(),
+                &[staking_pool.bump],
+            ];
+            let signer_seeds = &[&seeds[..]];
+
+            let burn_ctx = CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                Burn {
+                    mint: defla_mint.to_account_info(),
+                    from: staking_pool_token_account.to_account_info(),
+                    authority: staking_pool.to_account_info(),
+                },
+                signer_seeds,
+            );
+            token::burn(burn_ctx, total_penalty)?;
+            staking_pool.total_penalties_burned = staking_pool.total_penalties_burned.checked_add(total_penalty).unwrap();
+        }
+        PenaltyDestination::RewardVault => {
+            staking_pool.total_penalties_redistributed = staking_pool.total_penalties_redistributed.checked_add(total_penalty).unwrap();
+        }
+    }
+
+    Ok(())
+}
+
+// Helper function to burn the position NFT when a tokenized position is closed
+fn retire_position_nft<'info>(
+    stake_account: &StakeAccount,
+    position_mint: Option<&Account<'info, Mint>>,
+    position_token_account: Option<&Account<'info, TokenAccount>>,
+    holder: &Signer<'info>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+    let Some(expected_mint) = stake_account.position_mint else {
+        return Ok(());
+    };
+    let (Some(mint), Some(holder_account)) = (position_mint, position_token_account) else {
+        return err!(StakingError::PositionNftRequired);
+    };
+    require_keys_eq!(mint.key(), expected_mint, StakingError::PositionNftRequired);
+
+    let burn_ctx = CpiContext::new(
+        token_program.to_account_info(),
+        Burn {
+            mint: mint.to_account_info(),
+            from: holder_account.to_account_info(),
+            authority: holder.to_account_info(),
+        },
+    );
+    token::burn(burn_ctx, 1)
+}
 
 // Helper function to calculate pending rewards
-fn calculate_pending_rewards(stake_account: &Account<StakeAccount>, current_time: i64) -> Result<u64> {
+fn calculate_pending_rewards(
+    stake_account: &StakeAccount,
+    staking_pool: &StakingPool,
+    current_time: i64,
+) -> Result<u64> {
+    if staking_pool.dynamic_apr_enabled() {
+        // Integrate the utilization-based rate through the pool reward index
+        let index_delta = reward_index_at(staking_pool, current_time).saturating_sub(stake_account.reward_index_snapshot);
+        let base_rewards = stake_account.amount as u128 * index_delta / ACC_PRECISION;
+        return Ok((base_rewards * stake_account.multiplier_bps as u128 / BASIS_POINTS as u128) as u64);
+    }
+
     let time_elapsed = current_time - stake_account.last_claim_time;
     let days_elapsed = time_elapsed as f64 / SECONDS_PER_DAY as f64;
     
-    // Calculate compound interest: A = P(1 + r)^t
-    // Where r = daily rate, t = days elapsed
-    let daily_rate = DAILY_APR as f64 / BASIS_POINTS as f64;
-    let compound_factor = (1.0 + daily_rate).powf(days_elapsed);
-    let new_amount = stake_account.amount as f64 * compound_factor;
-    let rewards = (new_amount - stake_account.amount as f64) as u64;
+    // Calculate compound interest: A = P(1 + r)^t
+    // Where r = daily rate, t = days elapsed
+    let daily_rate = stake_account.daily_apr as f64 / BASIS_POINTS as f64;
+    let compound_factor = (1.0 + daily_rate).powf(days_elapsed);
+    let new_amount = stake_account.amount as f64 * compound_factor;
+    let base_rewards = new_amount - stake_account.amount as f64;
+
+    // Apply the lock-duration multiplier fixed at stake time
+    let rewards = (base_rewards * stake_account.multiplier_bps as f64 / BASIS_POINTS as f64) as u64;
+    
+    Ok(rewards)
+}
+
+// Helper function to grow liquid staking assets at the pool APR since the last update,
+// which raises the share price for every holder. Nothing accrues in emergency mode
+fn accrue_liquid_rewards(staking_pool: &mut StakingPool, current_time: i64) -> Result<()> {
+    accrue_reward_index(staking_pool, current_time);
+    let dynamic_apr = staking_pool.dynamic_apr_enabled();
+    let reward_index = staking_pool.reward_index;
+    let emergency_mode = staking_pool.emergency_mode;
+
+    let liquid = &mut staking_pool.liquid;
+    let time_elapsed = current_time - liquid.last_update_time;
+    let index_delta = reward_index.saturating_sub(liquid.reward_index_snapshot);
+    liquid.last_update_time = current_time;
+    liquid.reward_index_snapshot = reward_index;
+    if emergency_mode || time_elapsed <= 0 || liquid.total_assets == 0 {
+        return Ok(());
+    }
+
+    let rewards = if dynamic_apr {
+        (liquid.total_assets as u128 * index_delta / ACC_PRECISION) as u64
+    } else {
+        let days_elapsed = time_elapsed as f64 / SECONDS_PER_DAY as f64;
+        let daily_rate = staking_pool.config.daily_apr as f64 / BASIS_POINTS as f64;
+        let compound_factor = (1.0 + daily_rate).powf(days_elapsed);
+        let new_assets = (liquid.total_assets as f64 * compound_factor) as u64;
+        new_assets.saturating_sub(liquid.total_assets)
+    };
+
+    liquid.total_assets = liquid.total_assets.checked_add(rewards).unwrap();
+    liquid.total_rewards = liquid.total_rewards.checked_add(rewards).unwrap();
+    staking_pool.total_staked = staking_pool.total_staked.checked_add(rewards).unwrap();
+    update_share_price(staking_pool);
+
+    Ok(())
+}
+
+// Helper function to retire pool shares at the current share price, returning the underlying
+fn redeem_shares(staking_pool: &mut StakingPool, shares: u64) -> Result<u64> {
+    let liquid = &staking_pool.liquid;
+    require!(shares <= liquid.total_shares, StakingError::InvalidAmount);
+    let amount = (shares as u128 * liquid.total_assets as u128 / liquid.total_shares as u128) as u64;
+    require!(amount > 0, StakingError::InvalidAmount);
+
+    staking_pool.liquid.total_assets = staking_pool.liquid.total_assets.checked_sub(amount).unwrap();
+    staking_pool.liquid.total_shares = staking_pool.liquid.total_shares.checked_sub(shares).unwrap();
+    staking_pool.total_staked = staking_pool.total_staked.checked_sub(amount).unwrap();
+    update_share_price(staking_pool);
+
+    Ok(amount)
+}
+
+// Helper function to refresh the stored share price (underlying per share)
+fn update_share_price(staking_pool: &mut StakingPool) {
+    let liquid = &mut staking_pool.liquid;
+    liquid.share_price = if liquid.total_shares == 0 {
+        SHARE_PRICE_PRECISION as u64
+    } else {
+        (liquid.total_assets as u128 * SHARE_PRICE_PRECISION / liquid.total_shares as u128) as u64
+    };
+}
+
+// Helper function to resolve the daily APR from the pool's utilization curve,
+// interpolating linearly between points and clamping to the ends
+fn dynamic_daily_apr(staking_pool: &StakingPool) -> u64 {
+    let utilization = (staking_pool.total_staked as u128 * BASIS_POINTS as u128
+        / staking_pool.target_cap.max(1) as u128)
+        .min(BASIS_POINTS as u128) as u16;
+
+    apr_at_utilization(&staking_pool.apr_curve, utilization).unwrap_or(staking_pool.config.daily_apr)
+}
+
+// Helper function to evaluate the APR curve at a utilization, None for an empty curve
+fn apr_at_utilization(curve: &[AprCurvePoint], utilization: u16) -> Option<u64> {
+    let (first, last) = match (curve.first(), curve.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return None,
+    };
+
+    if utilization <= first.utilization_bps {
+        return Some(first.daily_apr);
+    }
+    if utilization >= last.utilization_bps {
+        return Some(last.daily_apr);
+    }
+
+    for window in curve.windows(2) {
+        let (low, high) = (&window[0], &window[1]);
+        if utilization <= high.utilization_bps {
+            let span = (high.utilization_bps - low.utilization_bps) as i128;
+            let offset = (utilization - low.utilization_bps) as i128;
+            let delta = high.daily_apr as i128 - low.daily_apr as i128;
+            return Some((low.daily_apr as i128 + delta * offset / span) as u64);
+        }
+    }
+
+    Some(last.daily_apr)
+}
+
+// Helper function to compute the reward index (rewards per staked token, scaled by
+// ACC_PRECISION) as of `current_time` without storing it
+fn reward_index_at(staking_pool: &StakingPool, current_time: i64) -> u128 {
+    if !staking_pool.dynamic_apr_enabled() {
+        return staking_pool.reward_index;
+    }
+
+    let elapsed = current_time.saturating_sub(staking_pool.reward_index_updated_at).max(0) as u128;
+    staking_pool.reward_index
+        + dynamic_daily_apr(staking_pool) as u128 * elapsed * ACC_PRECISION
+            / (BASIS_POINTS as u128 * SECONDS_PER_DAY as u128)
+}
+
+// Helper function to accrue the reward index at the rate in force. The rate depends on
+// total_staked, so this must run before every change to it
+fn accrue_reward_index(staking_pool: &mut StakingPool, current_time: i64) {
+    staking_pool.reward_index = reward_index_at(staking_pool, current_time);
+    staking_pool.reward_index_updated_at = current_time;
+}
+
+// Helper function to resolve the reward multiplier for a lock length from the pool curve,
+// interpolating linearly between points and clamping to the ends
+fn lock_multiplier_bps(lock_multipliers: &[LockMultiplier], lock_period_days: u32) -> u16 {
+    let (first, last) = match (lock_multipliers.first(), lock_multipliers.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return BASIS_POINTS as u16,
+    };
+
+    if lock_period_days <= first.lock_days {
+        return first.multiplier_bps;
+    }
+    if lock_period_days >= last.lock_days {
+        return last.multiplier_bps;
+    }
+
+    for window in lock_multipliers.windows(2) {
+        let (low, high) = (&window[0], &window[1]);
+        if lock_period_days <= high.lock_days {
+            let span = (high.lock_days - low.lock_days) as i64;
+            let offset = (lock_period_days - low.lock_days) as i64;
+            let delta = high.multiplier_bps as i64 - low.multiplier_bps as i64;
+            return (low.multiplier_bps as i64 + delta * offset / span) as u16;
+        }
+    }
+
+    last.multiplier_bps
+}
+
+// Helper function to read the authority of a deflationary_token config account.
+// Returns None when the account is not an initialized DeflationaryTokenConfig
+fn token_config_authority(token_config: &UncheckedAccount) -> Result<Option<Pubkey>> {
+    if token_config.owner != &DEFLATIONARY_TOKEN_PROGRAM_ID {
+        return Ok(None);
+    }
+
+    // Layout: 8-byte discriminator followed by `authority: Pubkey`
+    let data = token_config.try_borrow_data()?;
+    let discriminator = &anchor_lang::solana_program::hash::hash(b"account:DeflationaryTokenConfig").to_bytes()[..8];
+    if data.len() < 40 || &data[..8] != discriminator {
+        return Ok(None);
+    }
+
+    Ok(Some(Pubkey::try_from(&data[8..40]).unwrap()))
+}
+
+// Helper function to check that `signer` controls the position: the holder of the
+// position NFT when tokenized, otherwise the original owner
+fn authorize_position(
+    stake_account: &StakeAccount,
+    signer: &Pubkey,
+    position_token_account: Option<&Account<TokenAccount>>,
+) -> Result<()> {
+    match stake_account.position_mint {
+        Some(position_mint) => {
+            let holder_account = position_token_account.ok_or(StakingError::PositionNftRequired)?;
+            require_keys_eq!(holder_account.mint, position_mint, StakingError::PositionNftRequired);
+            require_keys_eq!(holder_account.owner, *signer, StakingError::InvalidOwner);
+            require!(holder_account.amount == 1, StakingError::PositionNftRequired);
+        }
+        None => require_keys_eq!(stake_account.owner, *signer, StakingError::InvalidOwner),
+    }
+
+    Ok(())
+}
+
+// Helper function to record the position and pool stake after a change
+fn record_checkpoints(
+    voter_checkpoints: &mut VoterCheckpoints,
+    pool_checkpoints: &mut PoolCheckpoints,
+    staking_pool: &StakingPool,
+    stake_account: &StakeAccount,
+    current_time: i64,
+) {
+    // Tokenized positions carry no voting power: the NFT can change hands while the
+    // checkpoints stay keyed to the original owner
+    let voting_amount = if stake_account.is_active && stake_account.position_mint.is_none() {
+        stake_account.amount
+    } else {
+        0
+    };
+    voter_checkpoints.record(VoterCheckpoint {
+        timestamp: current_time,
+        amount: voting_amount,
+        lock_end: stake_account.start_time + stake_account.lock_period,
+    });
+    pool_checkpoints.record(PoolCheckpoint {
+        timestamp: current_time,
+        total_staked: staking_pool.total_staked,
+        total_weighted_stake: staking_pool.total_weighted_stake,
+    });
+}
+
+// Helper function to compute voting power: stake weighted by the remaining lock
+fn voting_power(checkpoint: &VoterCheckpoint, timestamp: i64) -> u64 {
+    let remaining = (checkpoint.lock_end - timestamp).clamp(0, VOTING_POWER_MAX_LOCK);
+    (checkpoint.amount as u128 * remaining as u128 / VOTING_POWER_MAX_LOCK as u128) as u64
+}
+
+// Helper function to append to a checkpoint ring buffer. A checkpoint in the same
+// second as the latest one replaces it
+fn push_checkpoint<T: Copy>(
+    checkpoints: &mut Vec<T>,
+    next_index: &mut u16,
+    checkpoint: T,
+    timestamp_of: impl Fn(&T) -> i64,
+) {
+    if !checkpoints.is_empty() {
+        let latest = (*next_index as usize + checkpoints.len() - 1) % checkpoints.len();
+        if timestamp_of(&checkpoints[latest]) == timestamp_of(&checkpoint) {
+            checkpoints[latest] = checkpoint;
+            return;
+        }
+    }
+
+    if checkpoints.len() < MAX_CHECKPOINTS {
+        checkpoints.push(checkpoint);
+    } else {
+        checkpoints[*next_index as usize] = checkpoint;
+    }
+    *next_index = ((*next_index as usize + 1) % MAX_CHECKPOINTS) as u16;
+}
+
+// Helper function to find the latest checkpoint at or before `timestamp`. Fails when
+// the buffer has wrapped past that point
+fn checkpoint_at<T: Copy>(
+    checkpoints: &[T],
+    timestamp: i64,
+    timestamp_of: impl Fn(&T) -> i64,
+) -> Result<Option<T>> {
+    let found = checkpoints
+        .iter()
+        .filter(|checkpoint| timestamp_of(checkpoint) <= timestamp)
+        .max_by_key(|checkpoint| timestamp_of(checkpoint))
+        .copied();
+
+    let oldest = checkpoints.iter().map(&timestamp_of).min();
+    if checkpoints.len() == MAX_CHECKPOINTS && oldest.is_some_and(|oldest| timestamp < oldest) {
+        return err!(StakingError::CheckpointUnavailable);
+    }
+
+    Ok(found)
+}
+
+// Helper function to compute the multiplier-weighted stake used by reward stream accumulators
+fn weighted_amount(stake_account: &StakeAccount) -> u64 {
+    (stake_account.amount as u128 * stake_account.multiplier_bps as u128 / BASIS_POINTS as u128) as u64
+}
+
+// Helper function to bring every reward stream accumulator up to `current_time`.
+// Emissions are capped by what has been funded into each stream
+fn update_reward_streams(staking_pool: &mut StakingPool, current_time: i64) {
+    let total_weight = staking_pool.total_weighted_stake as u128;
+
+    for stream in staking_pool.reward_streams.iter_mut() {
+        let elapsed = current_time.saturating_sub(stream.last_update_time);
+        if elapsed > 0 && total_weight > 0 {
+            let unallocated = stream.total_funded.saturating_sub(stream.total_accrued) as u128;
+            let emitted = (stream.reward_rate as u128 * elapsed as u128).min(unallocated);
+            stream.acc_reward_per_share += emitted * ACC_PRECISION / total_weight;
+            stream.total_accrued += emitted as u64;
+        }
+        stream.last_update_time = current_time;
+    }
+}
+
+// Helper function to move stream rewards earned by the position into `accrued`.
+// Must run after `update_reward_streams` and before the position's weight changes
+fn settle_stream_rewards(staking_pool: &StakingPool, stake_account: &mut StakeAccount) {
+    let weight = weighted_amount(stake_account) as u128;
+    stake_account
+        .stream_rewards
+        .resize(staking_pool.reward_streams.len(), StreamPosition::default());
+
+    for (stream, position) in staking_pool.reward_streams.iter().zip(stake_account.stream_rewards.iter_mut()) {
+        let earned = weight * stream.acc_reward_per_share / ACC_PRECISION;
+        position.accrued += (earned - position.reward_debt) as u64;
+        position.reward_debt = earned;
+    }
+}
+
+// Helper function to re-anchor stream reward debts after the position's weight changed
+fn reset_stream_debts(staking_pool: &StakingPool, stake_account: &mut StakeAccount) {
+    let weight = weighted_amount(stake_account) as u128;
+    stake_account
+        .stream_rewards
+        .resize(staking_pool.reward_streams.len(), StreamPosition::default());
+
+    for (stream, position) in staking_pool.reward_streams.iter().zip(stake_account.stream_rewards.iter_mut()) {
+        position.reward_debt = weight * stream.acc_reward_per_share / ACC_PRECISION;
+    }
+}
+
+// Helper function to pay settled stream rewards. `remaining_accounts` holds
+// (stream vault, user token account) pairs; streams without a pair stay accrued
+// unless `require_all` is set. Returns whether anything was paid
+fn pay_stream_rewards<'info>(
+    staking_pool: &mut Account<'info, StakingPool>,
+    stake_account: &mut StakeAccount,
+    recipient: Pubkey,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    token_program: &Program<'info, Token>,
+    require_all: bool,
+) -> Result<bool> {
+    let pairs = remaining_accounts.chunks_exact(2);
+    require!(pairs.remainder().is_empty(), StakingError::InvalidRewardStream);
+
+    let mut any_paid = false;
+    let mut paid = vec![false; staking_pool.reward_streams.len()];
+    let defla_mint = staking_pool.defla_mint;
+    let seeds = &[
+        b"staking_pool",
+        defla_mint.as_ref(),
+        &[staking_pool.bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+    let timestamp = Clock::get()?.unix_timestamp;
+
+    for pair in pairs {
+        let (vault, user_token_account) = (&pair[0], &pair[1]);
+        let index = staking_pool
+            .reward_streams
+            .iter()
+            .position(|stream| stream.vault == vault.key())
+            .ok_or(StakingError::InvalidRewardStream)?;
+        require!(!paid[index], StakingError::InvalidRewardStream);
+        paid[index] = true;
+
+        let destination = Account::<TokenAccount>::try_from(user_token_account)?;
+        require_keys_eq!(destination.mint, staking_pool.reward_streams[index].mint, StakingError::InvalidRewardStream);
+        require_keys_eq!(destination.owner, recipient, StakingError::InvalidOwner);
+
+        let amount = stake_account.stream_rewards[index].accrued;
+        if amount == 0 {
+            continue;
+        }
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: vault.clone(),
+                to: user_token_account.clone(),
+                authority: staking_pool.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::transfer(transfer_ctx, amount)?;
+
+        stake_account.stream_rewards[index].accrued = 0;
+        any_paid = true;
+        let stream = &mut staking_pool.reward_streams[index];
+        stream.total_distributed = stream.total_distributed.checked_add(amount).unwrap();
+
+        emit!(StreamRewardsClaimedEvent {
+            user: recipient,
+            pool: staking_pool.key(),
+            mint: staking_pool.reward_streams[index].mint,
+            amount,
+            timestamp,
+        });
+    }
+
+    if require_all {
+        for (index, position) in stake_account.stream_rewards.iter().enumerate() {
+            require!(paid[index] || position.accrued == 0, StakingError::MissingRewardStreamAccounts);
+        }
+    }
+
+    Ok(any_paid)
+}
+
+// Helper function to turn rewards into principal without moving tokens out of the vault.
+// Extra reward streams are settled first since the position's weight changes
+fn compound_position(
+    staking_pool: &mut StakingPool,
+    stake_account: &mut StakeAccount,
+    amount: u64,
+    current_time: i64,
+) -> Result<()> {
+    check_stake_limits(staking_pool, stake_account.amount, amount)?;
+    update_reward_streams(staking_pool, current_time);
+    settle_stream_rewards(staking_pool, stake_account);
+    accrue_reward_index(staking_pool, current_time);
+    let old_weight = weighted_amount(stake_account);
+
+    stake_account.amount = stake_account.amount.checked_add(amount).unwrap();
+    stake_account.last_claim_time = current_time;
+    stake_account.reward_index_snapshot = staking_pool.reward_index;
+    stake_account.total_compounded = stake_account.total_compounded.checked_add(amount).unwrap();
+
+    staking_pool.total_staked = staking_pool.total_staked.checked_add(amount).unwrap();
+    staking_pool.total_weighted_stake = staking_pool
+        .total_weighted_stake
+        .checked_sub(old_weight)
+        .unwrap()
+        .checked_add(weighted_amount(stake_account))
+        .unwrap();
+    reset_stream_debts(staking_pool, stake_account);
+
+    Ok(())
+}
+
+// Helper function to enforce the pool capacity limit on new deposits
+fn check_pool_capacity(staking_pool: &StakingPool, amount: u64) -> Result<()> {
+    if staking_pool.max_total_staked > 0 {
+        let total_after = staking_pool.total_staked.checked_add(amount).ok_or(StakingError::PoolCapacityExceeded)?;
+        require!(total_after <= staking_pool.max_total_staked, StakingError::PoolCapacityExceeded);
+    }
+    Ok(())
+}
+
+// Helper function to enforce both the pool capacity and the per-wallet cap on a position top-up
+fn check_stake_limits(staking_pool: &StakingPool, position_amount: u64, amount: u64) -> Result<()> {
+    check_pool_capacity(staking_pool, amount)?;
+    if staking_pool.max_stake_per_wallet > 0 {
+        let position_after = position_amount.checked_add(amount).ok_or(StakingError::WalletStakeCapExceeded)?;
+        require!(position_after <= staking_pool.max_stake_per_wallet, StakingError::WalletStakeCapExceeded);
+    }
+    Ok(())
+}
+
+// Helper function to validate pool parameters
+fn validate_pool_config(config: &PoolConfig) -> Result<()> {
+    require!(config.min_stake_amount > 0, StakingError::InvalidPoolConfig);
+    require!(config.daily_apr <= MAX_DAILY_APR, StakingError::InvalidPoolConfig);
+    require!(
+        config.min_lock_period >= 0
+            && config.min_lock_period <= config.max_lock_period
+            && config.max_lock_period <= MAX_LOCK_PERIOD_LIMIT,
+        StakingError::InvalidPoolConfig
+    );
+    require!(
+        config.unbonding_period >= 0 && config.unbonding_period <= MAX_LOCK_PERIOD_LIMIT,
+        StakingError::InvalidPoolConfig
+    );
+
+    Ok(())
+}
+
+// Helper function to calculate the early withdrawal penalty as (principal penalty, forfeited rewards)
+fn calculate_early_withdrawal_penalty(
+    penalty_config: &PenaltyConfig,
+    stake_account: &StakeAccount,
+    principal: u64,
+    pending_rewards: u64,
+    current_time: i64,
+) -> (u64, u64) {
+    let principal_penalty = principal as u128 * penalty_config.principal_penalty_bps as u128 / BASIS_POINTS as u128;
+    let rewards_forfeited = pending_rewards as u128 * penalty_config.reward_forfeit_bps as u128 / BASIS_POINTS as u128;
+
+    if !penalty_config.scale_with_remaining_lock || stake_account.lock_period <= 0 {
+        return (principal_penalty as u64, rewards_forfeited as u64);
+    }
+
+    // Scale linearly with the share of the lock period still remaining
+    let lock_end = stake_account.start_time + stake_account.lock_period;
+    let remaining = (lock_end - current_time).clamp(0, stake_account.lock_period) as u128;
+    let lock_period = stake_account.lock_period as u128;
+
+    (
+        (principal_penalty * remaining / lock_period) as u64,
+        (rewards_forfeited * remaining / lock_period) as u64,
+    )
+}
+
+#[derive(Accounts)]
+pub struct InitializeRegistry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PoolRegistry::INIT_SPACE,
+        seeds = [b"pool_registry"],
+        bump
+    )]
+    pub registry: Account<'info, PoolRegistry>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::DeflaStaking>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ StakingError::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, PoolRegistry>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PoolRegistryEntry::INIT_SPACE,
+        seeds = [b"pool_entry", registry.pool_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub pool_entry: Account<'info, PoolRegistryEntry>,
+
+    /// CHECK: DeflationaryTokenConfig PDA of the mint, parsed in `token_config_authority`.
+    /// May be uninitialized for mints not launched through deflationary_token
+    #[account(
+        seeds = [b"token_config", defla_mint.key().as_ref()],
+        bump,
+        seeds::program = DEFLATIONARY_TOKEN_PROGRAM_ID
+    )]
+    pub token_config: UncheckedAccount<'info>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + StakingPool::INIT_SPACE,
+        seeds = [b"staking_pool", defla_mint.key().as_ref()],
+        bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PoolCheckpoints::INIT_SPACE,
+        seeds = [b"pool_checkpoints", staking_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_checkpoints: Account<'info, PoolCheckpoints>,
+    
+    pub defla_mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = defla_mint,
+        associated_token::authority = staking_pool,
+    )]
+    pub staking_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundRewards<'info> {
+    pub funder: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_pool", defla_mint.key().as_ref()],
+        bump = staking_pool.bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    pub defla_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = defla_mint,
+        token::authority = funder,
+    )]
+    pub funder_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = defla_mint,
+        associated_token::authority = staking_pool,
+    )]
+    pub staking_pool_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct AddRewardStream<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_pool", staking_pool.defla_mint.as_ref()],
+        bump = staking_pool.bump,
+        has_one = authority @ StakingError::Unauthorized
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    /// Must differ from the staked mint so stream funds stay out of the principal vault
+    #[account(constraint = reward_mint.key() != staking_pool.defla_mint @ StakingError::InvalidRewardMint)]
+    pub reward_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = reward_mint,
+        associated_token::authority = staking_pool,
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundRewardStream<'info> {
+    pub funder: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_pool", staking_pool.defla_mint.as_ref()],
+        bump = staking_pool.bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    #[account(mut)]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = reward_vault.mint,
+        token::authority = funder,
+    )]
+    pub funder_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct EnableLiquidMode<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_pool", defla_mint.key().as_ref()],
+        bump = staking_pool.bump,
+        has_one = authority @ StakingError::Unauthorized
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    pub defla_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"share_mint", staking_pool.key().as_ref()],
+        bump,
+        mint::decimals = defla_mint.decimals,
+        mint::authority = staking_pool,
+    )]
+    pub share_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct LiquidDeposit<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_pool", defla_mint.key().as_ref()],
+        bump = staking_pool.bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_checkpoints", staking_pool.key().as_ref()],
+        bump = pool_checkpoints.bump
+    )]
+    pub pool_checkpoints: Account<'info, PoolCheckpoints>,
+
+    pub defla_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        address = staking_pool.liquid.share_mint @ StakingError::LiquidModeDisabled
+    )]
+    pub share_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = defla_mint,
+        associated_token::authority = user,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = share_mint,
+        token::authority = user,
+    )]
+    pub user_share_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = defla_mint,
+        associated_token::authority = staking_pool,
+    )]
+    pub staking_pool_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(ticket_id: u64)]
+pub struct RequestLiquidRedeem<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_pool", staking_pool.defla_mint.as_ref()],
+        bump = staking_pool.bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_checkpoints", staking_pool.key().as_ref()],
+        bump = pool_checkpoints.bump
+    )]
+    pub pool_checkpoints: Account<'info, PoolCheckpoints>,
+
+    #[account(
+        mut,
+        address = staking_pool.liquid.share_mint @ StakingError::LiquidModeDisabled
+    )]
+    pub share_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = share_mint,
+        token::authority = user,
+    )]
+    pub user_share_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + RedemptionTicket::INIT_SPACE,
+        seeds = [b"redemption", staking_pool.key().as_ref(), user.key().as_ref(), ticket_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub redemption_ticket: Account<'info, RedemptionTicket>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimLiquidRedeem<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"redemption", staking_pool.key().as_ref(), user.key().as_ref(), redemption_ticket.ticket_id.to_le_bytes().as_ref()],
+        bump = redemption_ticket.bump,
+        constraint = redemption_ticket.owner == user.key() @ StakingError::InvalidOwner,
+        close = user
+    )]
+    pub redemption_ticket: Account<'info, RedemptionTicket>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_pool", defla_mint.key().as_ref()],
+        bump = staking_pool.bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    pub defla_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = defla_mint,
+        associated_token::authority = user,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = defla_mint,
+        associated_token::authority = staking_pool,
+    )]
+    pub staking_pool_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"staking_pool", defla_mint.key().as_ref()],
+        bump = staking_pool.bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + VoterCheckpoints::INIT_SPACE,
+        seeds = [b"voter_checkpoints", user.key().as_ref(), staking_pool.key().as_ref()],
+        bump
+    )]
+    pub voter_checkpoints: Account<'info, VoterCheckpoints>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_checkpoints", staking_pool.key().as_ref()],
+        bump = pool_checkpoints.bump
+    )]
+    pub pool_checkpoints: Account<'info, PoolCheckpoints>,
     
-    Ok(rewards)
+    #[account(
+        init,
+        payer = user,
+        space = 8 + StakeAccount::INIT_SPACE,
+        seeds = [b"stake_account", user.key().as_ref(), staking_pool.key().as_ref()],
+        bump
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+    
+    pub defla_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = defla_mint,
+        associated_token::authority = user,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = defla_mint,
+        associated_token::authority = staking_pool,
+    )]
+    pub staking_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"stake_account", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
+        bump = stake_account.bump
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    /// Holder's token account for the position NFT, required once the position is tokenized
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        seeds = [b"staking_pool", defla_mint.key().as_ref()],
+        bump = staking_pool.bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    /// Claimer's vesting escrow, required while the pool vests claimed rewards
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RewardEscrow::INIT_SPACE,
+        seeds = [b"reward_escrow", user.key().as_ref(), staking_pool.key().as_ref()],
+        bump
+    )]
+    pub reward_escrow: Option<Account<'info, RewardEscrow>>,
+    
+    pub defla_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = defla_mint,
+        associated_token::authority = user,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = defla_mint,
+        associated_token::authority = staking_pool,
+    )]
+    pub staking_pool_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawVestedRewards<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_escrow", user.key().as_ref(), staking_pool.key().as_ref()],
+        bump = reward_escrow.bump
+    )]
+    pub reward_escrow: Account<'info, RewardEscrow>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_pool", defla_mint.key().as_ref()],
+        bump = staking_pool.bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    #[account(mut)]
+    pub defla_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = defla_mint,
+        associated_token::authority = user,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = defla_mint,
+        associated_token::authority = staking_pool,
+    )]
+    pub staking_pool_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct TokenizePosition<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"stake_account", user.key().as_ref(), staking_pool.key().as_ref()],
+        bump = stake_account.bump,
+        constraint = stake_account.owner == user.key() @ StakingError::InvalidOwner,
+        constraint = stake_account.position_mint.is_none() @ StakingError::PositionAlreadyTokenized,
+        constraint = stake_account.is_active @ StakingError::StakeNotActive
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    #[account(
+        seeds = [b"staking_pool", staking_pool.defla_mint.as_ref()],
+        bump = staking_pool.bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"position_mint", stake_account.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = staking_pool,
+    )]
+    pub position_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = position_mint,
+        associated_token::authority = user,
+    )]
+    pub user_position_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"voter_checkpoints", user.key().as_ref(), staking_pool.key().as_ref()],
+        bump = voter_checkpoints.bump
+    )]
+    pub voter_checkpoints: Account<'info, VoterCheckpoints>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_checkpoints", staking_pool.key().as_ref()],
+        bump = pool_checkpoints.bump
+    )]
+    pub pool_checkpoints: Account<'info, PoolCheckpoints>,
+
+    /// CHECK: Metadata account, validated by the metadata program
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+
+    pub metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
+pub struct Compound<'info> {
+    pub user: Signer<'info>,
+
     #[account(
-        init,
-        payer = authority,
-        space = 8 + StakingPool::INIT_SPACE,
+        mut,
+        seeds = [b"stake_account", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
+        bump = stake_account.bump
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    /// Holder's token account for the position NFT, required once the position is tokenized
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_pool", staking_pool.defla_mint.as_ref()],
+        bump = staking_pool.bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"voter_checkpoints", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
+        bump = voter_checkpoints.bump
+    )]
+    pub voter_checkpoints: Account<'info, VoterCheckpoints>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_checkpoints", staking_pool.key().as_ref()],
+        bump = pool_checkpoints.bump
+    )]
+    pub pool_checkpoints: Account<'info, PoolCheckpoints>,
+}
+
+#[derive(Accounts)]
+pub struct SetAutoCompound<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"stake_account", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
+        bump = stake_account.bump
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    /// Holder's token account for the position NFT, required once the position is tokenized
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"staking_pool", staking_pool.defla_mint.as_ref()],
+        bump = staking_pool.bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+}
+
+#[derive(Accounts)]
+pub struct CrankCompound<'info> {
+    pub cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"stake_account", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
+        bump = stake_account.bump
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    #[account(
+        mut,
         seeds = [b"staking_pool", defla_mint.key().as_ref()],
-        bump
+        bump = staking_pool.bump
     )]
     pub staking_pool: Account<'info, StakingPool>,
-    
+
+    #[account(
+        mut,
+        seeds = [b"voter_checkpoints", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
+        bump = voter_checkpoints.bump
+    )]
+    pub voter_checkpoints: Account<'info, VoterCheckpoints>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_checkpoints", staking_pool.key().as_ref()],
+        bump = pool_checkpoints.bump
+    )]
+    pub pool_checkpoints: Account<'info, PoolCheckpoints>,
+
     pub defla_mint: Account<'info, Mint>,
-    
+
     #[account(
-        init,
-        payer = authority,
+        mut,
+        token::mint = defla_mint,
+        token::authority = cranker,
+    )]
+    pub cranker_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
         associated_token::mint = defla_mint,
         associated_token::authority = staking_pool,
     )]
     pub staking_pool_token_account: Account<'info, TokenAccount>,
-    
+
     pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct Stake<'info> {
+pub struct Unstake<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
     
+    #[account(
+        mut,
+        seeds = [b"stake_account", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
+        bump = stake_account.bump,
+        close = user
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    /// Holder's token account for the position NFT, required once the position is tokenized
+    #[account(mut)]
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub position_mint: Option<Account<'info, Mint>>,
+    
     #[account(
         mut,
         seeds = [b"staking_pool", defla_mint.key().as_ref()],
         bump = staking_pool.bump
     )]
     pub staking_pool: Account<'info, StakingPool>,
-    
+
     #[account(
-        init,
-        payer = user,
-        space = 8 + StakeAccount::INIT_SPACE,
-        seeds = [b"stake_account", user.key().as_ref(), staking_pool.key().as_ref()],
-        bump
+        mut,
+        seeds = [b"voter_checkpoints", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
+        bump = voter_checkpoints.bump
     )]
-    pub stake_account: Account<'info, StakeAccount>,
+    pub voter_checkpoints: Account<'info, VoterCheckpoints>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_checkpoints", staking_pool.key().as_ref()],
+        bump = pool_checkpoints.bump
+    )]
+    pub pool_checkpoints: Account<'info, PoolCheckpoints>,
     
+    #[account(mut)]
     pub defla_mint: Account<'info, Mint>,
     
     #[account(
@@ -261,97 +3099,473 @@ pub struct Stake<'info> {
     pub staking_pool_token_account: Account<'info, TokenAccount>,
     
     pub token_program: Program<'info, Token>,
+
+    /// Withdrawer's vesting escrow, required while the pool vests rewards
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RewardEscrow::INIT_SPACE,
+        seeds = [b"reward_escrow", user.key().as_ref(), staking_pool.key().as_ref()],
+        bump
+    )]
+    pub reward_escrow: Option<Account<'info, RewardEscrow>>,
+
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimRewards<'info> {
+pub struct UpdatePool<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"staking_pool", staking_pool.defla_mint.as_ref()],
+        bump = staking_pool.bump,
+        has_one = authority @ StakingError::Unauthorized
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+}
+
+#[derive(Accounts)]
+pub struct UnstakePartial<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    
+
     #[account(
         mut,
-        seeds = [b"stake_account", user.key().as_ref(), staking_pool.key().as_ref()],
-        bump = stake_account.bump,
-        has_one = owner @ StakingError::InvalidOwner
+        seeds = [b"stake_account", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
+        bump = stake_account.bump
     )]
     pub stake_account: Account<'info, StakeAccount>,
-    
+
+    /// Holder's token account for the position NFT, required once the position is tokenized
+    #[account(mut)]
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub position_mint: Option<Account<'info, Mint>>,
+
     #[account(
+        mut,
         seeds = [b"staking_pool", defla_mint.key().as_ref()],
         bump = staking_pool.bump
     )]
     pub staking_pool: Account<'info, StakingPool>,
-    
+
+    #[account(
+        mut,
+        seeds = [b"voter_checkpoints", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
+        bump = voter_checkpoints.bump
+    )]
+    pub voter_checkpoints: Account<'info, VoterCheckpoints>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_checkpoints", staking_pool.key().as_ref()],
+        bump = pool_checkpoints.bump
+    )]
+    pub pool_checkpoints: Account<'info, PoolCheckpoints>,
+
+    #[account(mut)]
     pub defla_mint: Account<'info, Mint>,
-    
+
     #[account(
         mut,
         associated_token::mint = defla_mint,
         associated_token::authority = user,
     )]
     pub user_token_account: Account<'info, TokenAccount>,
-    
+
     #[account(
         mut,
         associated_token::mint = defla_mint,
         associated_token::authority = staking_pool,
     )]
     pub staking_pool_token_account: Account<'info, TokenAccount>,
-    
+
     pub token_program: Program<'info, Token>,
+
+    /// Withdrawer's vesting escrow, required while the pool vests rewards
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RewardEscrow::INIT_SPACE,
+        seeds = [b"reward_escrow", user.key().as_ref(), staking_pool.key().as_ref()],
+        bump
+    )]
+    pub reward_escrow: Option<Account<'info, RewardEscrow>>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct Unstake<'info> {
+pub struct RequestUnstake<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    
+
     #[account(
         mut,
-        seeds = [b"stake_account", user.key().as_ref(), staking_pool.key().as_ref()],
+        seeds = [b"stake_account", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
+        bump = stake_account.bump
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    /// Holder's token account for the position NFT, required once the position is tokenized
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_pool", defla_mint.key().as_ref()],
+        bump = staking_pool.bump
+    )]
+    pub staking_pool: Account<'info, StakingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"voter_checkpoints", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
+        bump = voter_checkpoints.bump
+    )]
+    pub voter_checkpoints: Account<'info, VoterCheckpoints>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_checkpoints", staking_pool.key().as_ref()],
+        bump = pool_checkpoints.bump
+    )]
+    pub pool_checkpoints: Account<'info, PoolCheckpoints>,
+
+    #[account(mut)]
+    pub defla_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = defla_mint,
+        associated_token::authority = staking_pool,
+    )]
+    pub staking_pool_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// Withdrawer's vesting escrow, required while the pool vests rewards
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RewardEscrow::INIT_SPACE,
+        seeds = [b"reward_escrow", user.key().as_ref(), staking_pool.key().as_ref()],
+        bump
+    )]
+    pub reward_escrow: Option<Account<'info, RewardEscrow>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetVotingPower<'info> {
+    #[account(
+        seeds = [b"voter_checkpoints", voter_checkpoints.owner.as_ref(), voter_checkpoints.pool.as_ref()],
+        bump = voter_checkpoints.bump
+    )]
+    pub voter_checkpoints: Account<'info, VoterCheckpoints>,
+}
+
+#[derive(Accounts)]
+pub struct GetPoolStake<'info> {
+    #[account(
+        seeds = [b"pool_checkpoints", pool_checkpoints.pool.as_ref()],
+        bump = pool_checkpoints.bump
+    )]
+    pub pool_checkpoints: Account<'info, PoolCheckpoints>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawUnstaked<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"stake_account", stake_account.owner.as_ref(), staking_pool.key().as_ref()],
         bump = stake_account.bump,
-        has_one = owner @ StakingError::InvalidOwner,
         close = user
     )]
     pub stake_account: Account<'info, StakeAccount>,
-    
+
+    /// Holder's token account for the position NFT, required once the position is tokenized
+    #[account(mut)]
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub position_mint: Option<Account<'info, Mint>>,
+
     #[account(
         mut,
         seeds = [b"staking_pool", defla_mint.key().as_ref()],
         bump = staking_pool.bump
     )]
     pub staking_pool: Account<'info, StakingPool>,
-    
+
     pub defla_mint: Account<'info, Mint>,
-    
+
     #[account(
         mut,
         associated_token::mint = defla_mint,
         associated_token::authority = user,
     )]
     pub user_token_account: Account<'info, TokenAccount>,
-    
+
     #[account(
         mut,
         associated_token::mint = defla_mint,
         associated_token::authority = staking_pool,
     )]
     pub staking_pool_token_account: Account<'info, TokenAccount>,
-    
+
     pub token_program: Program<'info, Token>,
 }
 
+#[account]
+#[derive(InitSpace)]
+pub struct PoolRegistry {
+    pub admin: Pubkey,
+    pub pool_count: u64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PoolRegistryEntry {
+    pub pool: Pubkey,
+    pub defla_mint: Pubkey,
+    pub authority: Pubkey,
+    pub index: u64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct StakingPool {
     pub authority: Pubkey,
     pub defla_mint: Pubkey,
+    pub config: PoolConfig,
     pub total_staked: u64,
     pub total_stakers: u64,
+    pub penalty_config: PenaltyConfig,
+    pub total_penalties_burned: u64,
+    pub total_penalties_redistributed: u64,
+    #[max_len(MAX_MULTIPLIER_POINTS)]
+    pub lock_multipliers: Vec<LockMultiplier>,
+    pub total_rewards_funded: u64,
+    pub registry_index: u64,
+    pub total_weighted_stake: u64,
+    #[max_len(MAX_REWARD_STREAMS)]
+    pub reward_streams: Vec<RewardStream>,
+    pub liquid: LiquidState,
+    pub total_unbonding: u64,
+    pub pending_unbondings: u64,
+    pub paused: bool,
+    pub emergency_mode: bool,
+    pub max_total_staked: u64, // 0 = unlimited
+    pub max_stake_per_wallet: u64, // 0 = unlimited
+    pub reward_vesting_period: i64, // 0 = claims paid out directly
+    pub vesting_early_exit_bps: u16,
+    pub total_escrowed_rewards: u64,
+    pub target_cap: u64,
+    #[max_len(MAX_APR_CURVE_POINTS)]
+    pub apr_curve: Vec<AprCurvePoint>,
+    pub reward_index: u128, // dynamic APR accumulator, scaled by ACC_PRECISION
+    pub reward_index_updated_at: i64,
+    pub bump: u8,
+}
+
+impl StakingPool {
+    // Staking, claims and compounding are blocked while paused or in emergency mode
+    pub fn is_operational(&self) -> bool {
+        !self.paused && !self.emergency_mode
+    }
+
+    // Rewards follow the utilization curve instead of the fixed daily APR
+    pub fn dynamic_apr_enabled(&self) -> bool {
+        !self.apr_curve.is_empty() && self.target_cap > 0
+    }
+
+    // Daily APR currently in force for the pool
+    pub fn current_daily_apr(&self) -> u64 {
+        if self.dynamic_apr_enabled() {
+            dynamic_daily_apr(self)
+        } else {
+            self.config.daily_apr
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct LiquidState {
+    pub enabled: bool,
+    pub share_mint: Pubkey,
+    pub total_shares: u64,
+    pub total_assets: u64, // underlying backing the shares, including accrued rewards
+    pub share_price: u64, // exchange rate: underlying per share, scaled by SHARE_PRICE_PRECISION
+    pub total_rewards: u64,
+    pub pending_redemptions: u64,
+    pub cooldown_period: i64,
+    pub last_update_time: i64,
+    pub reward_index_snapshot: u128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct AprCurvePoint {
+    pub utilization_bps: u16, // total_staked as a share of target_cap
+    pub daily_apr: u64, // basis points per day
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct RewardStream {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub reward_rate: u64, // tokens per second across the whole pool
+    pub acc_reward_per_share: u128, // scaled by ACC_PRECISION
+    pub last_update_time: i64,
+    pub total_funded: u64,
+    pub total_accrued: u64,
+    pub total_distributed: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct StreamPosition {
+    pub reward_debt: u128,
+    pub accrued: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct LockMultiplier {
+    pub lock_days: u32,
+    pub multiplier_bps: u16, // 10000 = 1.0x
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct PoolConfig {
+    pub min_stake_amount: u64,
+    pub daily_apr: u64, // basis points per day
+    pub min_lock_period: i64, // seconds
+    pub max_lock_period: i64, // seconds
+    pub unbonding_period: i64, // seconds, 0 = unstake pays out immediately
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct PenaltyConfig {
+    pub principal_penalty_bps: u16,
+    pub reward_forfeit_bps: u16,
+    pub scale_with_remaining_lock: bool,
+    pub destination: PenaltyDestination,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub enum PenaltyDestination {
+    #[default]
+    Burn,
+    RewardVault,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct RedemptionTicket {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub ticket_id: u64,
+    pub amount: u64,
+    pub unlock_time: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct RewardEscrow {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub locked_amount: u64, // still vesting
+    pub vested_amount: u64, // vested, awaiting withdrawal
+    pub vesting_start: i64,
+    pub vesting_end: i64,
+    pub total_withdrawn: u64,
+    pub total_penalized: u64,
+    pub bump: u8,
+}
+
+impl RewardEscrow {
+    // Move the linearly vested part of the locked balance into the withdrawable balance
+    pub fn release(&mut self, current_time: i64) {
+        if self.locked_amount == 0 || current_time <= self.vesting_start {
+            return;
+        }
+
+        let released = if current_time >= self.vesting_end {
+            self.locked_amount
+        } else {
+            (self.locked_amount as u128 * (current_time - self.vesting_start) as u128
+                / (self.vesting_end - self.vesting_start) as u128) as u64
+        };
+        self.locked_amount -= released;
+        self.vested_amount += released;
+        self.vesting_start = current_time;
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct VoterCheckpoints {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub next_index: u16,
+    #[max_len(MAX_CHECKPOINTS)]
+    pub checkpoints: Vec<VoterCheckpoint>,
+    pub bump: u8,
+}
+
+impl VoterCheckpoints {
+    pub fn record(&mut self, checkpoint: VoterCheckpoint) {
+        push_checkpoint(&mut self.checkpoints, &mut self.next_index, checkpoint, |c| c.timestamp);
+    }
+
+    pub fn at(&self, timestamp: i64) -> Result<Option<VoterCheckpoint>> {
+        checkpoint_at(&self.checkpoints, timestamp, |c| c.timestamp)
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PoolCheckpoints {
+    pub pool: Pubkey,
+    pub next_index: u16,
+    #[max_len(MAX_CHECKPOINTS)]
+    pub checkpoints: Vec<PoolCheckpoint>,
     pub bump: u8,
 }
 
+impl PoolCheckpoints {
+    pub fn record(&mut self, checkpoint: PoolCheckpoint) {
+        push_checkpoint(&mut self.checkpoints, &mut self.next_index, checkpoint, |c| c.timestamp);
+    }
+
+    pub fn at(&self, timestamp: i64) -> Result<Option<PoolCheckpoint>> {
+        checkpoint_at(&self.checkpoints, timestamp, |c| c.timestamp)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct VoterCheckpoint {
+    pub timestamp: i64,
+    pub amount: u64,
+    pub lock_end: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct PoolCheckpoint {
+    pub timestamp: i64,
+    pub total_staked: u64,
+    pub total_weighted_stake: u64,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct StakeAccount {
@@ -359,17 +3573,48 @@ pub struct StakeAccount {
     pub amount: u64,
     pub start_time: i64,
     pub lock_period: i64,
+    pub multiplier_bps: u16,
+    pub daily_apr: u64,
+    pub unbonding_period: i64, // pool setting at stake time
     pub last_claim_time: i64,
+    pub reward_index_snapshot: u128,
     pub total_claimed: u64,
     pub is_active: bool,
+    #[max_len(MAX_REWARD_STREAMS)]
+    pub stream_rewards: Vec<StreamPosition>,
+    pub total_compounded: u64,
+    pub auto_compound: bool,
+    pub compound_bounty_bps: u16,
+    pub position_mint: Option<Pubkey>,
+    pub unbonding_amount: u64,
+    pub unbonding_principal: u64, // unbonding_amount without rewards, paid in emergency mode
+    pub unbonding_end: i64,
     pub bump: u8,
 }
 
+#[event]
+pub struct PoolCreatedEvent {
+    pub pool: Pubkey,
+    pub defla_mint: Pubkey,
+    pub authority: Pubkey,
+    pub registry_index: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsFundedEvent {
+    pub pool: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct StakeEvent {
     pub user: Pubkey,
     pub amount: u64,
     pub lock_period: i64,
+    pub multiplier_bps: u16,
     pub timestamp: i64,
 }
 
@@ -377,6 +3622,96 @@ pub struct StakeEvent {
 pub struct ClaimEvent {
     pub user: Pubkey,
     pub amount: u64,
+    pub daily_apr: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidDepositEvent {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub share_price: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidRedeemRequestedEvent {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub ticket_id: u64,
+    pub shares: u64,
+    pub amount: u64,
+    pub unlock_time: i64,
+    pub share_price: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyLiquidRedeemEvent {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub shares: u64,
+    pub amount: u64,
+    pub share_price: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidRedeemClaimedEvent {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub ticket_id: u64,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionTokenizedEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub stake_account: Pubkey,
+    pub position_mint: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CompoundEvent {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub amount_compounded: u64,
+    pub bounty: u64,
+    pub cranker: Option<Pubkey>,
+    pub new_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StreamRewardsClaimedEvent {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardStreamUpdatedEvent {
+    pub pool: Pubkey,
+    pub index: u8,
+    pub mint: Pubkey,
+    pub reward_rate: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardStreamFundedEvent {
+    pub pool: Pubkey,
+    pub index: u8,
+    pub mint: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
     pub timestamp: i64,
 }
 
@@ -386,16 +3721,147 @@ pub struct UnstakeEvent {
     pub amount: u64,
     pub rewards: u64,
     pub early_withdrawal: bool,
+    pub principal_penalty: u64,
+    pub rewards_forfeited: u64,
+    pub penalty_destination: PenaltyDestination,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PartialUnstakeEvent {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub remaining: u64,
+    pub rewards: u64,
+    pub early_withdrawal: bool,
+    pub principal_penalty: u64,
+    pub rewards_forfeited: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UnbondingRequestedEvent {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub rewards: u64,
+    pub early_withdrawal: bool,
+    pub principal_penalty: u64,
+    pub rewards_forfeited: u64,
+    pub unbonding_end: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UnbondingWithdrawnEvent {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolPausedEvent {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyModeEvent {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub enabled: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyWithdrawEvent {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AprCurveUpdatedEvent {
+    pub pool: Pubkey,
+    pub target_cap: u64,
+    pub apr_curve: Vec<AprCurvePoint>,
+    pub current_daily_apr: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolConfigUpdatedEvent {
+    pub pool: Pubkey,
+    pub old_config: PoolConfig,
+    pub new_config: PoolConfig,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardVestingUpdatedEvent {
+    pub pool: Pubkey,
+    pub vesting_period: i64,
+    pub early_exit_penalty_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsEscrowedEvent {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub locked_amount: u64,
+    pub vesting_end: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VestedRewardsWithdrawnEvent {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub penalty: u64,
+    pub remaining_locked: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PenaltyConfigUpdatedEvent {
+    pub pool: Pubkey,
+    pub principal_penalty_bps: u16,
+    pub reward_forfeit_bps: u16,
+    pub scale_with_remaining_lock: bool,
+    pub destination: PenaltyDestination,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakeLimitsUpdatedEvent {
+    pub pool: Pubkey,
+    pub max_total_staked: u64,
+    pub max_stake_per_wallet: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LockMultipliersUpdatedEvent {
+    pub pool: Pubkey,
+    pub lock_multipliers: Vec<LockMultiplier>,
     pub timestamp: i64,
 }
 
 #[error_code]
 pub enum StakingError {
-    #[msg("Insufficient stake amount. Minimum is 10,000 HELL")]
+    #[msg("Insufficient stake amount. Below the pool minimum")]
     InsufficientAmount,
-    #[msg("Lock period too short. Minimum is 7 days")]
+    #[msg("Lock period too short for this pool")]
     LockPeriodTooShort,
-    #[msg("Lock period too long. Maximum is 120 days")]
+    #[msg("Lock period too long for this pool")]
     LockPeriodTooLong,
     #[msg("Stake account is not active")]
     StakeNotActive,
@@ -403,4 +3869,259 @@ pub enum StakingError {
     NoRewardsToClaim,
     #[msg("Invalid owner")]
     InvalidOwner,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Penalty too high. Maximum principal penalty is 50%")]
+    PenaltyTooHigh,
+    #[msg("Too many multiplier points. Maximum is 8")]
+    TooManyMultiplierPoints,
+    #[msg("Invalid multiplier. Must be between 1.0x and 5.0x")]
+    InvalidMultiplier,
+    #[msg("Multiplier curve lock lengths must be strictly increasing")]
+    InvalidMultiplierCurve,
+    #[msg("Invalid pool configuration")]
+    InvalidPoolConfig,
+    #[msg("Amount must be greater than zero")]
+    InvalidAmount,
+    #[msg("Too many reward streams. Maximum is 4")]
+    TooManyRewardStreams,
+    #[msg("Reward stream already exists for this mint")]
+    RewardStreamExists,
+    #[msg("Reward stream mint cannot be the staked mint")]
+    InvalidRewardMint,
+    #[msg("Invalid reward stream")]
+    InvalidRewardStream,
+    #[msg("Accounts for every reward stream with accrued rewards are required")]
+    MissingRewardStreamAccounts,
+    #[msg("Compound bounty too high. Maximum is 5%")]
+    BountyTooHigh,
+    #[msg("Auto-compound is not enabled for this position")]
+    AutoCompoundDisabled,
+    #[msg("Liquid staking is not enabled for this pool")]
+    LiquidModeDisabled,
+    #[msg("Liquid staking is already enabled for this pool")]
+    LiquidModeAlreadyEnabled,
+    #[msg("Cooldown period has not elapsed")]
+    CooldownNotElapsed,
+    #[msg("Name too long. Maximum 32 characters")]
+    NameTooLong,
+    #[msg("Symbol too long. Maximum 10 characters")]
+    SymbolTooLong,
+    #[msg("URI too long. Maximum 200 characters")]
+    UriTooLong,
+    #[msg("Position is already tokenized")]
+    PositionAlreadyTokenized,
+    #[msg("The position NFT is required for this position")]
+    PositionNftRequired,
+    #[msg("This pool requires request_unstake and withdraw_unstaked")]
+    UnbondingRequired,
+    #[msg("Position is not unbonding")]
+    NotUnbonding,
+    #[msg("Remaining stake would fall below the pool minimum")]
+    RemainderBelowMinimum,
+    #[msg("Pool is paused")]
+    PoolPaused,
+    #[msg("Pool is not in emergency mode")]
+    EmergencyModeDisabled,
+    #[msg("Emergency mode is active, only emergency withdrawals are allowed")]
+    EmergencyModeActive,
+    #[msg("Checkpoint history does not reach back to the requested time")]
+    CheckpointUnavailable,
+    #[msg("Invalid APR curve")]
+    InvalidAprCurve,
+    #[msg("Pool must be empty to switch between fixed and dynamic APR")]
+    PoolNotEmpty,
+    #[msg("Deposit would exceed the pool's staking capacity")]
+    PoolCapacityExceeded,
+    #[msg("Deposit would exceed the per-wallet stake cap")]
+    WalletStakeCapExceeded,
+    #[msg("Liquid deposits are disabled while a per-wallet stake cap is set")]
+    LiquidDepositsCapped,
+    #[msg("Invalid reward vesting configuration")]
+    InvalidVestingConfig,
+    #[msg("Reward escrow account is required while claims vest")]
+    RewardEscrowRequired,
+    #[msg("Compounding is disabled while claimed rewards vest")]
+    CompoundingWhileVesting,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voter_checkpoint(timestamp: i64, amount: u64) -> VoterCheckpoint {
+        VoterCheckpoint { timestamp, amount, lock_end: timestamp + VOTING_POWER_MAX_LOCK }
+    }
+
+    fn recorded(timestamps: impl Iterator<Item = i64>) -> VoterCheckpoints {
+        let mut voter_checkpoints = VoterCheckpoints {
+            owner: Pubkey::default(),
+            pool: Pubkey::default(),
+            next_index: 0,
+            checkpoints: Vec::new(),
+            bump: 0,
+        };
+        for timestamp in timestamps {
+            voter_checkpoints.record(voter_checkpoint(timestamp, timestamp as u64));
+        }
+        voter_checkpoints
+    }
+
+    #[test]
+    fn checkpoints_replace_within_the_same_second() {
+        let mut voter_checkpoints = recorded([10, 20].into_iter());
+        voter_checkpoints.record(voter_checkpoint(20, 99));
+
+        assert_eq!(voter_checkpoints.checkpoints.len(), 2);
+        assert_eq!(voter_checkpoints.next_index, 2);
+        assert_eq!(voter_checkpoints.at(25).unwrap().unwrap().amount, 99);
+        assert_eq!(voter_checkpoints.at(15).unwrap().unwrap().amount, 10);
+        assert!(voter_checkpoints.at(5).unwrap().is_none());
+    }
+
+    #[test]
+    fn checkpoints_wrap_around_and_drop_the_oldest() {
+        let total = MAX_CHECKPOINTS as i64 + 3;
+        let voter_checkpoints = recorded((1..=total).map(|i| i * 10));
+
+        assert_eq!(voter_checkpoints.checkpoints.len(), MAX_CHECKPOINTS);
+        assert_eq!(voter_checkpoints.next_index, 3);
+        assert_eq!(voter_checkpoints.at(total * 10).unwrap().unwrap().amount, (total * 10) as u64);
+        assert_eq!(voter_checkpoints.at(45).unwrap().unwrap().amount, 40);
+        assert!(voter_checkpoints.at(35).is_err());
+    }
+
+    #[test]
+    fn same_second_replacement_after_wraparound() {
+        let total = MAX_CHECKPOINTS as i64 + 1;
+        let mut voter_checkpoints = recorded((1..=total).map(|i| i * 10));
+        voter_checkpoints.record(voter_checkpoint(total * 10, 7));
+
+        assert_eq!(voter_checkpoints.next_index, 1);
+        assert_eq!(voter_checkpoints.checkpoints[0].amount, 7);
+        assert_eq!(voter_checkpoints.at(total * 10).unwrap().unwrap().amount, 7);
+    }
+
+    #[test]
+    fn lock_multiplier_interpolates_and_clamps() {
+        let curve = [
+            LockMultiplier { lock_days: 30, multiplier_bps: 10000 },
+            LockMultiplier { lock_days: 130, multiplier_bps: 20000 },
+        ];
+
+        assert_eq!(lock_multiplier_bps(&[], 365), BASIS_POINTS as u16);
+        assert_eq!(lock_multiplier_bps(&curve, 0), 10000);
+        assert_eq!(lock_multiplier_bps(&curve, 30), 10000);
+        assert_eq!(lock_multiplier_bps(&curve, 80), 15000);
+        assert_eq!(lock_multiplier_bps(&curve, 130), 20000);
+        assert_eq!(lock_multiplier_bps(&curve, 1000), 20000);
+    }
+
+    #[test]
+    fn apr_curve_interpolates_and_clamps() {
+        let curve = [
+            AprCurvePoint { utilization_bps: 2000, daily_apr: 100 },
+            AprCurvePoint { utilization_bps: 6000, daily_apr: 20 },
+            AprCurvePoint { utilization_bps: 10000, daily_apr: 0 },
+        ];
+
+        assert_eq!(apr_at_utilization(&[], 5000), None);
+        assert_eq!(apr_at_utilization(&curve, 0), Some(100));
+        assert_eq!(apr_at_utilization(&curve, 2000), Some(100));
+        assert_eq!(apr_at_utilization(&curve, 4000), Some(60));
+        assert_eq!(apr_at_utilization(&curve, 6000), Some(20));
+        assert_eq!(apr_at_utilization(&curve, 8000), Some(10));
+        assert_eq!(apr_at_utilization(&curve, 10000), Some(0));
+    }
+
+    fn escrow(locked_amount: u64, vesting_start: i64, vesting_end: i64) -> RewardEscrow {
+        RewardEscrow {
+            owner: Pubkey::default(),
+            pool: Pubkey::default(),
+            locked_amount,
+            vested_amount: 0,
+            vesting_start,
+            vesting_end,
+            total_withdrawn: 0,
+            total_penalized: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn escrow_releases_linearly() {
+        let mut reward_escrow = escrow(1000, 100, 200);
+
+        reward_escrow.release(100);
+        assert_eq!((reward_escrow.locked_amount, reward_escrow.vested_amount), (1000, 0));
+
+        reward_escrow.release(125);
+        assert_eq!((reward_escrow.locked_amount, reward_escrow.vested_amount), (750, 250));
+
+        reward_escrow.release(150);
+        assert_eq!((reward_escrow.locked_amount, reward_escrow.vested_amount), (500, 500));
+
+        reward_escrow.release(500);
+        assert_eq!((reward_escrow.locked_amount, reward_escrow.vested_amount), (0, 1000));
+    }
+
+    fn liquid_pool(total_assets: u64, total_shares: u64) -> StakingPool {
+        let mut staking_pool = StakingPool {
+            authority: Pubkey::default(),
+            defla_mint: Pubkey::default(),
+            config: PoolConfig {
+                min_stake_amount: 0,
+                daily_apr: 100,
+                min_lock_period: 0,
+                max_lock_period: 0,
+                unbonding_period: 0,
+            },
+            total_staked: total_assets,
+            total_stakers: 0,
+            penalty_config: PenaltyConfig::default(),
+            total_penalties_burned: 0,
+            total_penalties_redistributed: 0,
+            lock_multipliers: Vec::new(),
+            total_rewards_funded: 0,
+            registry_index: 0,
+            total_weighted_stake: 0,
+            reward_streams: Vec::new(),
+            liquid: LiquidState { enabled: true, total_assets, total_shares, ..LiquidState::default() },
+            total_unbonding: 0,
+            pending_unbondings: 0,
+            paused: false,
+            emergency_mode: false,
+            max_total_staked: 0,
+            max_stake_per_wallet: 0,
+            reward_vesting_period: 0,
+            vesting_early_exit_bps: 0,
+            total_escrowed_rewards: 0,
+            target_cap: 0,
+            apr_curve: Vec::new(),
+            reward_index: 0,
+            reward_index_updated_at: 0,
+            bump: 0,
+        };
+        update_share_price(&mut staking_pool);
+        staking_pool
+    }
+
+    #[test]
+    fn emergency_redeem_pays_the_settled_share_price() {
+        let mut staking_pool = liquid_pool(1000, 1000);
+        accrue_liquid_rewards(&mut staking_pool, SECONDS_PER_DAY).unwrap();
+        let settled_assets = staking_pool.liquid.total_assets;
+        assert!(settled_assets > 1000);
+
+        // Nothing accrues once emergency mode is set
+        staking_pool.emergency_mode = true;
+        accrue_liquid_rewards(&mut staking_pool, 30 * SECONDS_PER_DAY).unwrap();
+        assert_eq!(staking_pool.liquid.total_assets, settled_assets);
+        assert_eq!(staking_pool.liquid.last_update_time, 30 * SECONDS_PER_DAY);
+
+        assert_eq!(redeem_shares(&mut staking_pool, 400).unwrap(), settled_assets * 400 / 1000);
+        assert_eq!(staking_pool.liquid.total_shares, 600);
+        assert_eq!(staking_pool.total_staked, staking_pool.liquid.total_assets);
+        assert!(redeem_shares(&mut staking_pool, 601).is_err());
+    }
 }
@@ -1,23 +1,31 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
-    associated_token::AssociatedToken,
+    associated_token::{get_associated_token_address, AssociatedToken},
     metadata::{
         create_metadata_accounts_v3, mpl_token_metadata::types::{Creator, DataV2},
         CreateMetadataAccountsV3, Metadata,
     },
-    token::{self, Mint, Token, TokenAccount, Transfer, Burn, MintTo},
+    token::{self, spl_token::instruction::AuthorityType, Mint, Token, TokenAccount, Transfer, Burn, MintTo, SetAuthority},
 };
+use defla_staking::StakeAccount;
 
 declare_id!("4hFLbSpLEWEvtw1Q6qPubs2QLAMfdiMafzUhyiifDY8T");
 
 const MAX_BURN_RATE: u16 = 1000; // 10% máximo
 const BASIS_POINTS: u16 = 10000;
+const MAX_DISCOUNT_TIERS: usize = 8;
+const REFLECTION_PRECISION: u128 = 1_000_000_000_000;
+const MAX_SCHEDULE_STEPS: usize = 8;
+const MAX_SIZE_TIERS: usize = 8;
+const BURN_RATE_TIMELOCK: i64 = 2 * 24 * 60 * 60; // 48 horas
+const MAX_ANTI_SNIPER_WINDOW: i64 = 24 * 60 * 60; // 24 horas
 
 #[program]
 pub mod deflationary_token {
     use super::*;
 
     /// Crear un nuevo token deflacionario
+    #[allow(clippy::too_many_arguments)]
     pub fn create_deflationary_token(
         ctx: Context<CreateDeflationaryToken>,
         token_name: String,
@@ -27,6 +35,7 @@ pub mod deflationary_token {
         initial_supply: u64,
         burn_rate: u16, // En basis points (100 = 1%)
         max_supply: u64,
+        launch_safety: LaunchSafetyOptions,
     ) -> Result<()> {
         require!(burn_rate <= MAX_BURN_RATE, DeflationaryError::BurnRateTooHigh);
         require!(token_name.len() <= 32, DeflationaryError::NameTooLong);
@@ -49,6 +58,29 @@ pub mod deflationary_token {
         token_config.total_burned = 0;
         token_config.created_at = clock.unix_timestamp;
         token_config.is_active = true;
+        token_config.staking_pool = Pubkey::default();
+        token_config.staking_discount_tiers = Vec::new();
+        token_config.burn_split = BurnSplit::default();
+        token_config.reward_vault = Pubkey::default();
+        token_config.treasury = Pubkey::default();
+        token_config.total_to_stakers = 0;
+        token_config.total_to_treasury = 0;
+        token_config.reflection_per_token = 0;
+        token_config.total_registered_balance = 0;
+        token_config.total_reflected = 0;
+        token_config.burn_schedule = Vec::new();
+        token_config.supply_floor = 0;
+        token_config.size_tiers = Vec::new();
+        token_config.anti_sniper = AntiSniperConfig::default();
+        token_config.directional_rates = DirectionalRates::default();
+        token_config.total_burned_on_buys = 0;
+        token_config.total_burned_on_sells = 0;
+        token_config.pending_burn_rate = None;
+        token_config.burn_rate_effective_at = 0;
+        token_config.pending_rates = None;
+        token_config.rates_effective_at = 0;
+        token_config.pending_authority = None;
+        token_config.launch_safety = launch_safety;
         token_config.bump = ctx.bumps.token_config;
 
         // Crear metadata del token
@@ -82,7 +114,7 @@ pub mod deflationary_token {
                 },
             ),
             data_v2,
-            true, // is_mutable
+            !launch_safety.immutable_metadata, // is_mutable
             true, // update_authority_is_signer
             None, // collection_details
         )?;
@@ -102,6 +134,34 @@ pub mod deflationary_token {
             )?;
         }
 
+        // Revocar autoridades según las opciones de lanzamiento seguro
+        if launch_safety.revoke_mint_authority {
+            token::set_authority(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    SetAuthority {
+                        current_authority: ctx.accounts.authority.to_account_info(),
+                        account_or_mint: ctx.accounts.mint.to_account_info(),
+                    },
+                ),
+                AuthorityType::MintTokens,
+                None,
+            )?;
+        }
+        if launch_safety.revoke_freeze_authority {
+            token::set_authority(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    SetAuthority {
+                        current_authority: ctx.accounts.authority.to_account_info(),
+                        account_or_mint: ctx.accounts.mint.to_account_info(),
+                    },
+                ),
+                AuthorityType::FreezeAccount,
+                None,
+            )?;
+        }
+
         emit!(TokenCreatedEvent {
             mint: ctx.accounts.mint.key(),
             creator: ctx.accounts.authority.key(),
@@ -109,6 +169,9 @@ pub mod deflationary_token {
             symbol: token_config.symbol.clone(),
             initial_supply,
             burn_rate,
+            decimals,
+            launch_safety,
+            safe_launch: token_config.is_safe_launch(),
             timestamp: clock.unix_timestamp,
         });
 
@@ -120,12 +183,101 @@ pub mod deflationary_token {
         ctx: Context<TransferWithBurn>,
         amount: u64,
     ) -> Result<()> {
+        let clock = Clock::get()?;
         let token_config = &mut ctx.accounts.token_config;
         require!(token_config.is_active, DeflationaryError::TokenNotActive);
+        token_config.apply_pending_burn_rate(clock.unix_timestamp);
+        token_config.apply_pending_rates(clock.unix_timestamp);
+
+        // Liquidar la reflection de emisor y receptor registrados con sus saldos previos
+        let mut sender_rewards = load_holder_rewards(&ctx.accounts.sender_rewards, ctx.accounts.from.key())?;
+        let mut recipient_rewards = if ctx.accounts.to.key() == ctx.accounts.from.key() {
+            None
+        } else {
+            load_holder_rewards(&ctx.accounts.recipient_rewards, ctx.accounts.to.key())?
+        };
+        if let Some(holder_rewards) = sender_rewards.as_mut() {
+            sync_holder(token_config, holder_rewards, ctx.accounts.from.amount);
+        }
+        if let Some(holder_rewards) = recipient_rewards.as_mut() {
+            sync_holder(token_config, holder_rewards, ctx.accounts.to.amount);
+        }
+
+        // Descuento para quien tenga una posición activa en el pool de staking
+        let discount_bps = match &ctx.accounts.stake_account {
+            Some(stake_account) => {
+                let (expected, _) = Pubkey::find_program_address(
+                    &[
+                        b"stake_account",
+                        ctx.accounts.authority.key().as_ref(),
+                        token_config.staking_pool.as_ref(),
+                    ],
+                    &defla_staking::ID,
+                );
+                require!(
+                    stake_account.key() == expected && stake_account.owner == ctx.accounts.authority.key(),
+                    DeflationaryError::InvalidStakeAccount
+                );
+                // Una posición tokenizada la controla quien tenga el NFT, no `owner`
+                require!(stake_account.position_mint.is_none(), DeflationaryError::TokenizedStakeAccount);
+                if stake_account.is_active {
+                    staking_discount_bps(&token_config.staking_discount_tiers, stake_account.amount)
+                } else {
+                    0
+                }
+            }
+            None => 0,
+        };
+        // Las cuentas del sistema registradas como exentas no pagan quema
+        let exempt = ctx.accounts.source_exemption.is_some() || ctx.accounts.destination_exemption.is_some();
 
-        // Calcular cantidad a quemar
-        let burn_amount = (amount as u128 * token_config.burn_rate as u128 / BASIS_POINTS as u128) as u64;
-        let transfer_amount = amount.checked_sub(burn_amount).unwrap();
+        // Compra si los tokens salen de un pool registrado, venta si entran en él
+        let direction = match (
+            is_initialized(&ctx.accounts.source_pool_vault),
+            is_initialized(&ctx.accounts.destination_pool_vault),
+        ) {
+            (true, false) => TransferDirection::Buy,
+            (false, true) => TransferDirection::Sell,
+            _ => TransferDirection::Transfer,
+        };
+        let (base_rate, size_tier) = if exempt {
+            (0, None)
+        } else {
+            token_config.transfer_burn_rate(amount, direction, clock.unix_timestamp)
+        };
+        let burn_rate = (base_rate as u32 * (BASIS_POINTS - discount_bps) as u32 / BASIS_POINTS as u32) as u16;
+
+        // Protección anti-sniper tras el lanzamiento; la exención solo cubre la quema
+        enforce_anti_sniper(
+            &token_config.anti_sniper,
+            ctx.accounts.wallet_cooldown.as_deref_mut(),
+            ctx.bumps.wallet_cooldown,
+            ctx.accounts.authority.key(),
+            token_config.mint,
+            amount,
+            clock.unix_timestamp,
+        )?;
+
+        // Calcular cantidad deducida y repartirla entre quema, stakers y tesorería
+        let deducted_amount = (amount as u128 * burn_rate as u128 / BASIS_POINTS as u128) as u64;
+        let mut transfer_amount = amount.checked_sub(deducted_amount).unwrap();
+        let split = token_config.burn_split;
+        let staking_amount = (deducted_amount as u128 * split.staking_bps as u128 / BASIS_POINTS as u128) as u64;
+        let treasury_amount = (deducted_amount as u128 * split.treasury_bps as u128 / BASIS_POINTS as u128) as u64;
+        // Sin holders registrados la parte de reflection se quema
+        let reflection_amount = if token_config.total_registered_balance > 0 {
+            (deducted_amount as u128 * split.reflection_bps as u128 / BASIS_POINTS as u128) as u64
+        } else {
+            0
+        };
+        let mut burn_amount = deducted_amount - staking_amount - treasury_amount - reflection_amount;
+
+        // No quemar por debajo del suelo de supply; el exceso llega al destinatario
+        let burnable = token_config.current_supply.saturating_sub(token_config.supply_floor);
+        if burn_amount > burnable {
+            transfer_amount = transfer_amount.checked_add(burn_amount - burnable).unwrap();
+            burn_amount = burnable;
+        }
 
         // Transferir tokens (cantidad - burn)
         if transfer_amount > 0 {
@@ -142,6 +294,61 @@ pub mod deflationary_token {
             )?;
         }
 
+        // Enviar la parte de los stakers al vault de recompensas
+        if staking_amount > 0 {
+            let reward_vault = ctx.accounts.reward_vault.as_ref().ok_or(DeflationaryError::MissingSplitAccount)?;
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.from.to_account_info(),
+                        to: reward_vault.to_account_info(),
+                        authority: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                staking_amount,
+            )?;
+            token_config.total_to_stakers = token_config.total_to_stakers.checked_add(staking_amount).unwrap();
+        }
+
+        // Enviar la parte del creador a la tesorería
+        if treasury_amount > 0 {
+            let treasury = ctx.accounts.treasury.as_ref().ok_or(DeflationaryError::MissingSplitAccount)?;
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.from.to_account_info(),
+                        to: treasury.to_account_info(),
+                        authority: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                treasury_amount,
+            )?;
+            token_config.total_to_treasury = token_config.total_to_treasury.checked_add(treasury_amount).unwrap();
+        }
+
+        // Repartir la parte de reflection entre los holders registrados
+        if reflection_amount > 0 {
+            let reflection_vault = ctx.accounts.reflection_vault.as_ref().ok_or(DeflationaryError::MissingSplitAccount)?;
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.from.to_account_info(),
+                        to: reflection_vault.to_account_info(),
+                        authority: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                reflection_amount,
+            )?;
+            token_config.reflection_per_token = token_config
+                .reflection_per_token
+                .checked_add(reflection_amount as u128 * REFLECTION_PRECISION / token_config.total_registered_balance as u128)
+                .unwrap();
+            token_config.total_reflected = token_config.total_reflected.checked_add(reflection_amount).unwrap();
+        }
+
         // Quemar tokens
         if burn_amount > 0 {
             token::burn(
@@ -159,6 +366,27 @@ pub mod deflationary_token {
             // Actualizar estadísticas
             token_config.total_burned = token_config.total_burned.checked_add(burn_amount).unwrap();
             token_config.current_supply = token_config.current_supply.checked_sub(burn_amount).unwrap();
+            match direction {
+                TransferDirection::Buy => {
+                    token_config.total_burned_on_buys = token_config.total_burned_on_buys.checked_add(burn_amount).unwrap();
+                }
+                TransferDirection::Sell => {
+                    token_config.total_burned_on_sells = token_config.total_burned_on_sells.checked_add(burn_amount).unwrap();
+                }
+                TransferDirection::Transfer => {}
+            }
+        }
+
+        // Actualizar los saldos registrados de emisor y receptor
+        if let Some(mut holder_rewards) = sender_rewards {
+            ctx.accounts.from.reload()?;
+            sync_holder(token_config, &mut holder_rewards, ctx.accounts.from.amount);
+            store_holder_rewards(&ctx.accounts.sender_rewards, &holder_rewards)?;
+        }
+        if let Some(mut holder_rewards) = recipient_rewards {
+            ctx.accounts.to.reload()?;
+            sync_holder(token_config, &mut holder_rewards, ctx.accounts.to.amount);
+            store_holder_rewards(&ctx.accounts.recipient_rewards, &holder_rewards)?;
         }
 
         emit!(TransferWithBurnEvent {
@@ -167,6 +395,483 @@ pub mod deflationary_token {
             to: ctx.accounts.to.owner,
             amount_transferred: transfer_amount,
             amount_burned: burn_amount,
+            amount_to_stakers: staking_amount,
+            amount_to_treasury: treasury_amount,
+            amount_reflected: reflection_amount,
+            burn_rate,
+            size_tier,
+            direction,
+            discount_bps,
+            exempt,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Activar o pausar el token
+    pub fn set_active(ctx: Context<UpdateTokenConfig>, is_active: bool) -> Result<()> {
+        let token_config = &mut ctx.accounts.token_config;
+        token_config.is_active = is_active;
+
+        emit!(TokenActiveUpdatedEvent {
+            mint: token_config.mint,
+            authority: token_config.authority,
+            is_active,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Programar un nuevo burn_rate, que entra en vigor pasado el timelock
+    pub fn update_burn_rate(ctx: Context<UpdateTokenConfig>, new_burn_rate: u16) -> Result<()> {
+        require!(new_burn_rate <= MAX_BURN_RATE, DeflationaryError::BurnRateTooHigh);
+
+        let clock = Clock::get()?;
+        let token_config = &mut ctx.accounts.token_config;
+
+        // Aplicar un cambio anterior ya vencido antes de programar el siguiente
+        token_config.apply_pending_burn_rate(clock.unix_timestamp);
+        let effective_at = clock.unix_timestamp.checked_add(BURN_RATE_TIMELOCK).unwrap();
+        token_config.pending_burn_rate = Some(new_burn_rate);
+        token_config.burn_rate_effective_at = effective_at;
+
+        emit!(BurnRateUpdateQueuedEvent {
+            mint: token_config.mint,
+            authority: token_config.authority,
+            current_burn_rate: token_config.burn_rate,
+            new_burn_rate,
+            effective_at,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Cancelar el cambio de tasas programado antes de que entre en vigor
+    pub fn cancel_rate_update(ctx: Context<UpdateTokenConfig>) -> Result<()> {
+        let clock = Clock::get()?;
+        let token_config = &mut ctx.accounts.token_config;
+        token_config.apply_pending_rates(clock.unix_timestamp);
+        require!(token_config.pending_rates.is_some(), DeflationaryError::NoPendingRateUpdate);
+        token_config.pending_rates = None;
+        token_config.rates_effective_at = 0;
+
+        emit!(RateUpdateCancelledEvent {
+            mint: token_config.mint,
+            authority: token_config.authority,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Proponer una nueva autoridad; debe aceptarla con accept_authority
+    pub fn propose_authority(ctx: Context<UpdateTokenConfig>, new_authority: Pubkey) -> Result<()> {
+        let token_config = &mut ctx.accounts.token_config;
+        token_config.pending_authority = Some(new_authority);
+
+        emit!(AuthorityProposedEvent {
+            mint: token_config.mint,
+            authority: token_config.authority,
+            proposed_authority: new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Aceptar la autoridad propuesta
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let token_config = &mut ctx.accounts.token_config;
+        let previous_authority = token_config.authority;
+        token_config.authority = ctx.accounts.new_authority.key();
+        token_config.pending_authority = None;
+
+        emit!(AuthorityTransferredEvent {
+            mint: token_config.mint,
+            previous_authority,
+            new_authority: token_config.authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Programar el reparto de la cantidad deducida entre quema, vault de staking y tesorería,
+    /// que entra en vigor pasado el timelock
+    pub fn set_burn_split(
+        ctx: Context<UpdateTokenConfig>,
+        burn_split: BurnSplit,
+        reward_vault: Pubkey,
+        treasury: Pubkey,
+    ) -> Result<()> {
+        require!(
+            burn_split.staking_bps as u32 + burn_split.treasury_bps as u32 + burn_split.reflection_bps as u32
+                <= BASIS_POINTS as u32,
+            DeflationaryError::InvalidBurnSplit
+        );
+        require!(
+            burn_split.staking_bps == 0 || reward_vault != Pubkey::default(),
+            DeflationaryError::InvalidBurnSplit
+        );
+        require!(
+            burn_split.treasury_bps == 0 || treasury != Pubkey::default(),
+            DeflationaryError::InvalidBurnSplit
+        );
+
+        let clock = Clock::get()?;
+        let token_config = &mut ctx.accounts.token_config;
+        // La parte de stakers solo puede ir al vault del pool de defla_staking de este mint
+        if reward_vault != Pubkey::default() {
+            let (staking_pool, _) = Pubkey::find_program_address(
+                &[b"staking_pool", token_config.mint.as_ref()],
+                &defla_staking::ID,
+            );
+            require!(
+                reward_vault == get_associated_token_address(&staking_pool, &token_config.mint),
+                DeflationaryError::InvalidRewardVault
+            );
+        }
+        let mut rates = token_config.rate_config(clock.unix_timestamp);
+        rates.burn_split = burn_split;
+        rates.reward_vault = reward_vault;
+        rates.treasury = treasury;
+        let effective_at = token_config.queue_rate_config(rates, clock.unix_timestamp)?;
+
+        emit!(BurnSplitUpdatedEvent {
+            mint: token_config.mint,
+            staking_bps: burn_split.staking_bps,
+            treasury_bps: burn_split.treasury_bps,
+            reflection_bps: burn_split.reflection_bps,
+            reward_vault,
+            treasury,
+            effective_at,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Programar los descuentos de quema para holders con HELL en staking, que entran en
+    /// vigor pasado el timelock
+    pub fn set_staking_discount_tiers(
+        ctx: Context<UpdateTokenConfig>,
+        staking_pool: Pubkey,
+        tiers: Vec<StakingDiscountTier>,
+    ) -> Result<()> {
+        require!(tiers.len() <= MAX_DISCOUNT_TIERS, DeflationaryError::InvalidDiscountTiers);
+        for (i, tier) in tiers.iter().enumerate() {
+            require!(tier.discount_bps <= BASIS_POINTS, DeflationaryError::InvalidDiscountTiers);
+            if i > 0 {
+                require!(
+                    tier.min_staked > tiers[i - 1].min_staked,
+                    DeflationaryError::InvalidDiscountTiers
+                );
+            }
+        }
+
+        let clock = Clock::get()?;
+        let token_config = &mut ctx.accounts.token_config;
+        let mut rates = token_config.rate_config(clock.unix_timestamp);
+        rates.staking_pool = staking_pool;
+        rates.staking_discount_tiers = tiers.clone();
+        let effective_at = token_config.queue_rate_config(rates, clock.unix_timestamp)?;
+
+        emit!(StakingDiscountTiersUpdatedEvent {
+            mint: token_config.mint,
+            staking_pool,
+            tiers,
+            effective_at,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Programar el calendario de tasas de quema y el suelo de supply, que entran en vigor
+    /// pasado el timelock
+    pub fn set_burn_schedule(
+        ctx: Context<UpdateTokenConfig>,
+        burn_schedule: Vec<BurnScheduleStep>,
+        supply_floor: u64,
+    ) -> Result<()> {
+        validate_burn_schedule(&burn_schedule)?;
+
+        let clock = Clock::get()?;
+        let token_config = &mut ctx.accounts.token_config;
+        require!(supply_floor <= token_config.max_supply, DeflationaryError::InvalidBurnSchedule);
+        let mut rates = token_config.rate_config(clock.unix_timestamp);
+        rates.burn_schedule = burn_schedule.clone();
+        rates.supply_floor = supply_floor;
+        let effective_at = token_config.queue_rate_config(rates, clock.unix_timestamp)?;
+
+        emit!(BurnScheduleUpdatedEvent {
+            mint: token_config.mint,
+            burn_schedule,
+            supply_floor,
+            effective_at,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Programar tasas de quema mayores para transferencias grandes, que entran en vigor
+    /// pasado el timelock
+    pub fn set_size_tiers(ctx: Context<UpdateTokenConfig>, size_tiers: Vec<SizeTier>) -> Result<()> {
+        require!(size_tiers.len() <= MAX_SIZE_TIERS, DeflationaryError::InvalidSizeTiers);
+        for (i, tier) in size_tiers.iter().enumerate() {
+            require!(tier.burn_rate <= MAX_BURN_RATE, DeflationaryError::BurnRateTooHigh);
+            require!(
+                tier.min_supply_bps > 0 && tier.min_supply_bps <= BASIS_POINTS,
+                DeflationaryError::InvalidSizeTiers
+            );
+            if i > 0 {
+                require!(
+                    tier.min_supply_bps > size_tiers[i - 1].min_supply_bps,
+                    DeflationaryError::InvalidSizeTiers
+                );
+            }
+        }
+
+        let clock = Clock::get()?;
+        let token_config = &mut ctx.accounts.token_config;
+        let mut rates = token_config.rate_config(clock.unix_timestamp);
+        rates.size_tiers = size_tiers.clone();
+        let effective_at = token_config.queue_rate_config(rates, clock.unix_timestamp)?;
+
+        emit!(SizeTiersUpdatedEvent {
+            mint: token_config.mint,
+            size_tiers,
+            effective_at,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Configurar la ventana anti-sniper (máximo 24 horas), medida desde la creación o desde el
+    /// inicio del lanzamiento
+    pub fn set_anti_sniper(ctx: Context<SetAntiSniper>, params: AntiSniperParams) -> Result<()> {
+        require!(
+            (0..=MAX_ANTI_SNIPER_WINDOW).contains(&params.window_seconds) && params.cooldown_seconds >= 0,
+            DeflationaryError::InvalidAntiSniperConfig
+        );
+
+        let window_start = if params.from_launch_start {
+            ctx.accounts
+                .launch_config
+                .as_ref()
+                .ok_or(DeflationaryError::InvalidAntiSniperConfig)?
+                .start_time
+        } else {
+            ctx.accounts.token_config.created_at
+        };
+
+        let token_config = &mut ctx.accounts.token_config;
+        token_config.anti_sniper = AntiSniperConfig {
+            window_start,
+            window_end: window_start
+                .checked_add(params.window_seconds)
+                .ok_or(DeflationaryError::InvalidAntiSniperConfig)?,
+            max_tx_amount: params.max_tx_amount,
+            cooldown_seconds: params.cooldown_seconds,
+        };
+
+        emit!(AntiSniperUpdatedEvent {
+            mint: token_config.mint,
+            window_start,
+            window_end: token_config.anti_sniper.window_end,
+            max_tx_amount: params.max_tx_amount,
+            cooldown_seconds: params.cooldown_seconds,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Consultar la tasa de quema vigente (sin descuentos ni exenciones)
+    pub fn get_effective_burn_rate(ctx: Context<GetEffectiveBurnRate>) -> Result<u16> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let mut token_config = ctx.accounts.token_config.clone().into_inner();
+        token_config.apply_pending_rates(current_time);
+        Ok(token_config.effective_burn_rate(current_time))
+    }
+
+    /// Eximir de la quema las transferencias desde o hacia una cuenta del sistema
+    pub fn add_burn_exemption(ctx: Context<AddBurnExemption>, address: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+        let exemption = &mut ctx.accounts.exemption;
+        exemption.mint = ctx.accounts.token_config.mint;
+        exemption.address = address;
+        exemption.added_at = clock.unix_timestamp;
+        exemption.bump = ctx.bumps.exemption;
+
+        emit!(BurnExemptionUpdatedEvent {
+            mint: exemption.mint,
+            address,
+            exempt: true,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Quitar una exención de quema
+    pub fn remove_burn_exemption(ctx: Context<RemoveBurnExemption>) -> Result<()> {
+        emit!(BurnExemptionUpdatedEvent {
+            mint: ctx.accounts.exemption.mint,
+            address: ctx.accounts.exemption.address,
+            exempt: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Programar tasas de quema distintas para compras y ventas contra pools registrados, que
+    /// entran en vigor pasado el timelock
+    pub fn set_directional_burn_rates(
+        ctx: Context<UpdateTokenConfig>,
+        directional_rates: DirectionalRates,
+    ) -> Result<()> {
+        require!(
+            directional_rates.buy_burn_rate <= MAX_BURN_RATE && directional_rates.sell_burn_rate <= MAX_BURN_RATE,
+            DeflationaryError::BurnRateTooHigh
+        );
+
+        let clock = Clock::get()?;
+        let token_config = &mut ctx.accounts.token_config;
+        let mut rates = token_config.rate_config(clock.unix_timestamp);
+        rates.directional_rates = directional_rates;
+        let effective_at = token_config.queue_rate_config(rates, clock.unix_timestamp)?;
+
+        emit!(DirectionalRatesUpdatedEvent {
+            mint: token_config.mint,
+            enabled: directional_rates.enabled,
+            buy_burn_rate: directional_rates.buy_burn_rate,
+            sell_burn_rate: directional_rates.sell_burn_rate,
+            effective_at,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Registrar la token account de un pool de liquidez para detectar compras y ventas
+    pub fn add_pool_vault(ctx: Context<AddPoolVault>, vault: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+        let pool_vault = &mut ctx.accounts.pool_vault;
+        pool_vault.mint = ctx.accounts.token_config.mint;
+        pool_vault.vault = vault;
+        pool_vault.added_at = clock.unix_timestamp;
+        pool_vault.bump = ctx.bumps.pool_vault;
+
+        emit!(PoolVaultUpdatedEvent {
+            mint: pool_vault.mint,
+            vault,
+            registered: true,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Quitar un pool de liquidez registrado
+    pub fn remove_pool_vault(ctx: Context<RemovePoolVault>) -> Result<()> {
+        emit!(PoolVaultUpdatedEvent {
+            mint: ctx.accounts.pool_vault.mint,
+            vault: ctx.accounts.pool_vault.vault,
+            registered: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Registrar una cuenta de recompensas de reflection para un holder
+    pub fn register_holder_rewards(ctx: Context<RegisterHolderRewards>) -> Result<()> {
+        let token_config = &mut ctx.accounts.token_config;
+        let holder_rewards = &mut ctx.accounts.holder_rewards;
+
+        holder_rewards.owner = ctx.accounts.owner.key();
+        holder_rewards.mint = ctx.accounts.mint.key();
+        holder_rewards.token_account = ctx.accounts.holder_token_account.key();
+        holder_rewards.balance = 0;
+        holder_rewards.reward_debt = 0;
+        holder_rewards.pending_rewards = 0;
+        holder_rewards.total_claimed = 0;
+        holder_rewards.bump = ctx.bumps.holder_rewards;
+        sync_holder(token_config, holder_rewards, ctx.accounts.holder_token_account.amount);
+
+        emit!(HolderRegisteredEvent {
+            mint: holder_rewards.mint,
+            owner: holder_rewards.owner,
+            balance: holder_rewards.balance,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sincronizar el saldo registrado con el saldo real del holder (cualquiera puede llamarlo)
+    pub fn sync_holder_rewards(ctx: Context<SyncHolderRewards>) -> Result<()> {
+        sync_holder(
+            &mut ctx.accounts.token_config,
+            &mut ctx.accounts.holder_rewards,
+            ctx.accounts.holder_token_account.amount,
+        );
+
+        Ok(())
+    }
+
+    /// Reclamar las recompensas de reflection acumuladas
+    pub fn claim_holder_rewards(ctx: Context<ClaimHolderRewards>) -> Result<()> {
+        let token_config = &mut ctx.accounts.token_config;
+        let holder_rewards = &mut ctx.accounts.holder_rewards;
+
+        sync_holder(token_config, holder_rewards, ctx.accounts.holder_token_account.amount);
+        let amount = holder_rewards.pending_rewards;
+        require!(amount > 0, DeflationaryError::NoHolderRewards);
+
+        holder_rewards.pending_rewards = 0;
+        holder_rewards.total_claimed = holder_rewards.total_claimed.checked_add(amount).unwrap();
+
+        let mint_key = ctx.accounts.mint.key();
+        let seeds = &[
+            b"token_config",
+            mint_key.as_ref(),
+            &[token_config.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.reflection_vault.to_account_info(),
+                    to: ctx.accounts.holder_token_account.to_account_info(),
+                    authority: token_config.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )?;
+
+        // El saldo cambió con el cobro
+        ctx.accounts.holder_token_account.reload()?;
+        sync_holder(
+            &mut ctx.accounts.token_config,
+            &mut ctx.accounts.holder_rewards,
+            ctx.accounts.holder_token_account.amount,
+        );
+
+        emit!(HolderRewardsClaimedEvent {
+            mint: mint_key,
+            owner: ctx.accounts.owner.key(),
+            amount,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
@@ -198,6 +903,19 @@ pub mod deflationary_token {
             .checked_div(1_000_000_000) // Ajustar por decimales de SOL
             .unwrap();
 
+        // Protección anti-sniper tras el lanzamiento, si el mint tiene configuración
+        if let Some(token_config) = load_token_config(&ctx.accounts.token_config)? {
+            enforce_anti_sniper(
+                &token_config.anti_sniper,
+                ctx.accounts.wallet_cooldown.as_deref_mut(),
+                ctx.bumps.wallet_cooldown,
+                ctx.accounts.participant.key(),
+                ctx.accounts.mint.key(),
+                tokens_to_receive,
+                clock.unix_timestamp,
+            )?;
+        }
+
         require!(
             launch_config.tokens_sold.checked_add(tokens_to_receive).unwrap() <= launch_config.total_tokens_for_sale,
             DeflationaryError::NotEnoughTokensLeft
@@ -271,7 +989,7 @@ pub mod deflationary_token {
 }
 
 #[derive(Accounts)]
-#[instruction(token_name: String)]
+#[instruction(token_name: String, token_symbol: String, token_uri: String, decimals: u8)]
 pub struct CreateDeflationaryToken<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
@@ -279,7 +997,7 @@ pub struct CreateDeflationaryToken<'info> {
     #[account(
         init,
         payer = authority,
-        mint::decimals = 6,
+        mint::decimals = decimals,
         mint::authority = authority,
         mint::freeze_authority = authority,
     )]
@@ -315,8 +1033,325 @@ pub struct CreateDeflationaryToken<'info> {
 
 #[derive(Accounts)]
 pub struct TransferWithBurn<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_config", mint.key().as_ref()],
+        bump = token_config.bump
+    )]
+    pub token_config: Account<'info, DeflationaryTokenConfig>,
+
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = authority,
+    )]
+    pub from: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+    )]
+    pub to: Account<'info, TokenAccount>,
+
+    /// Posición de staking del emisor, opcional para obtener el descuento de quema.
+    /// Las posiciones tokenizadas no dan descuento
+    pub stake_account: Option<Account<'info, StakeAccount>>,
+
+    /// Vault de recompensas de defla_staking, requerido si el reparto incluye stakers
+    #[account(
+        mut,
+        address = token_config.reward_vault,
+        token::mint = mint,
+    )]
+    pub reward_vault: Option<Account<'info, TokenAccount>>,
+
+    /// Tesorería del creador, requerida si el reparto incluye tesorería
+    #[account(
+        mut,
+        address = token_config.treasury,
+        token::mint = mint,
+    )]
+    pub treasury: Option<Account<'info, TokenAccount>>,
+
+    /// Vault de reflection, requerido si el reparto incluye holders
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = token_config,
+    )]
+    pub reflection_vault: Option<Account<'info, TokenAccount>>,
+
+    /// Exenciones de quema del dueño de origen o destino, si existen
+    #[account(
+        seeds = [b"burn_exemption", mint.key().as_ref(), from.owner.as_ref()],
+        bump = source_exemption.bump
+    )]
+    pub source_exemption: Option<Account<'info, BurnExemption>>,
+
+    #[account(
+        seeds = [b"burn_exemption", mint.key().as_ref(), to.owner.as_ref()],
+        bump = destination_exemption.bump
+    )]
+    pub destination_exemption: Option<Account<'info, BurnExemption>>,
+
+    /// CHECK: registro de pool de la cuenta de origen; si está inicializado es una compra
+    #[account(
+        seeds = [b"pool_vault", mint.key().as_ref(), from.key().as_ref()],
+        bump
+    )]
+    pub source_pool_vault: UncheckedAccount<'info>,
+
+    /// CHECK: registro de pool de la cuenta de destino; si está inicializado es una venta
+    #[account(
+        seeds = [b"pool_vault", mint.key().as_ref(), to.key().as_ref()],
+        bump
+    )]
+    pub destination_pool_vault: UncheckedAccount<'info>,
+
+    /// CHECK: cuenta de reflection del emisor; siempre en su PDA y se sincroniza si está registrada
+    #[account(
+        mut,
+        seeds = [b"holder_rewards", mint.key().as_ref(), from.owner.as_ref()],
+        bump
+    )]
+    pub sender_rewards: UncheckedAccount<'info>,
+
+    /// CHECK: cuenta de reflection del receptor; siempre en su PDA y se sincroniza si está registrada
+    #[account(
+        mut,
+        seeds = [b"holder_rewards", mint.key().as_ref(), to.owner.as_ref()],
+        bump
+    )]
+    pub recipient_rewards: UncheckedAccount<'info>,
+
+    /// Cooldown del emisor, requerido durante la ventana anti-sniper
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + WalletCooldown::INIT_SPACE,
+        seeds = [b"wallet_cooldown", mint.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub wallet_cooldown: Option<Account<'info, WalletCooldown>>,
+
+    pub system_program: Program<'info, System>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTokenConfig<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_config", token_config.mint.as_ref()],
+        bump = token_config.bump,
+        has_one = authority @ DeflationaryError::Unauthorized
+    )]
+    pub token_config: Account<'info, DeflationaryTokenConfig>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub new_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_config", token_config.mint.as_ref()],
+        bump = token_config.bump,
+        constraint = token_config.pending_authority == Some(new_authority.key()) @ DeflationaryError::Unauthorized
+    )]
+    pub token_config: Account<'info, DeflationaryTokenConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetAntiSniper<'info> {
     pub authority: Signer<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"token_config", token_config.mint.as_ref()],
+        bump = token_config.bump,
+        has_one = authority @ DeflationaryError::Unauthorized
+    )]
+    pub token_config: Account<'info, DeflationaryTokenConfig>,
+
+    /// Requerido si la ventana se mide desde el inicio del lanzamiento; debe haberlo creado
+    /// la autoridad del token, ya que setup_token_launch está abierto a cualquiera
+    #[account(
+        seeds = [b"launch_config", token_config.mint.as_ref()],
+        bump = launch_config.bump,
+        constraint = launch_config.authority == token_config.authority @ DeflationaryError::Unauthorized
+    )]
+    pub launch_config: Option<Account<'info, LaunchConfig>>,
+}
+
+#[derive(Accounts)]
+pub struct GetEffectiveBurnRate<'info> {
+    #[account(
+        seeds = [b"token_config", token_config.mint.as_ref()],
+        bump = token_config.bump
+    )]
+    pub token_config: Account<'info, DeflationaryTokenConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct AddBurnExemption<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"token_config", token_config.mint.as_ref()],
+        bump = token_config.bump,
+        has_one = authority @ DeflationaryError::Unauthorized
+    )]
+    pub token_config: Account<'info, DeflationaryTokenConfig>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + BurnExemption::INIT_SPACE,
+        seeds = [b"burn_exemption", token_config.mint.as_ref(), address.as_ref()],
+        bump
+    )]
+    pub exemption: Account<'info, BurnExemption>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveBurnExemption<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"token_config", token_config.mint.as_ref()],
+        bump = token_config.bump,
+        has_one = authority @ DeflationaryError::Unauthorized
+    )]
+    pub token_config: Account<'info, DeflationaryTokenConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"burn_exemption", token_config.mint.as_ref(), exemption.address.as_ref()],
+        bump = exemption.bump,
+        close = authority
+    )]
+    pub exemption: Account<'info, BurnExemption>,
+}
+
+#[derive(Accounts)]
+#[instruction(vault: Pubkey)]
+pub struct AddPoolVault<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"token_config", token_config.mint.as_ref()],
+        bump = token_config.bump,
+        has_one = authority @ DeflationaryError::Unauthorized
+    )]
+    pub token_config: Account<'info, DeflationaryTokenConfig>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PoolVault::INIT_SPACE,
+        seeds = [b"pool_vault", token_config.mint.as_ref(), vault.as_ref()],
+        bump
+    )]
+    pub pool_vault: Account<'info, PoolVault>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemovePoolVault<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"token_config", token_config.mint.as_ref()],
+        bump = token_config.bump,
+        has_one = authority @ DeflationaryError::Unauthorized
+    )]
+    pub token_config: Account<'info, DeflationaryTokenConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", token_config.mint.as_ref(), pool_vault.vault.as_ref()],
+        bump = pool_vault.bump,
+        close = authority
+    )]
+    pub pool_vault: Account<'info, PoolVault>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterHolderRewards<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_config", mint.key().as_ref()],
+        bump = token_config.bump
+    )]
+    pub token_config: Account<'info, DeflationaryTokenConfig>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        associated_token::mint = mint,
+        associated_token::authority = owner,
+    )]
+    pub holder_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + HolderRewards::INIT_SPACE,
+        seeds = [b"holder_rewards", mint.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub holder_rewards: Account<'info, HolderRewards>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SyncHolderRewards<'info> {
+    #[account(
+        mut,
+        seeds = [b"token_config", mint.key().as_ref()],
+        bump = token_config.bump
+    )]
+    pub token_config: Account<'info, DeflationaryTokenConfig>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"holder_rewards", mint.key().as_ref(), holder_rewards.owner.as_ref()],
+        bump = holder_rewards.bump
+    )]
+    pub holder_rewards: Account<'info, HolderRewards>,
+
+    #[account(address = holder_rewards.token_account @ DeflationaryError::InvalidHolderAccount)]
+    pub holder_token_account: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimHolderRewards<'info> {
+    pub owner: Signer<'info>,
+
     #[account(
         mut,
         seeds = [b"token_config", mint.key().as_ref()],
@@ -324,21 +1359,25 @@ pub struct TransferWithBurn<'info> {
     )]
     pub token_config: Account<'info, DeflationaryTokenConfig>,
 
-    #[account(mut)]
     pub mint: Account<'info, Mint>,
 
     #[account(
         mut,
-        token::mint = mint,
-        token::authority = authority,
+        seeds = [b"holder_rewards", mint.key().as_ref(), owner.key().as_ref()],
+        bump = holder_rewards.bump,
+        has_one = owner @ DeflationaryError::Unauthorized
     )]
-    pub from: Account<'info, TokenAccount>,
+    pub holder_rewards: Account<'info, HolderRewards>,
+
+    #[account(mut, address = holder_rewards.token_account @ DeflationaryError::InvalidHolderAccount)]
+    pub holder_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        token::mint = mint,
+        associated_token::mint = mint,
+        associated_token::authority = token_config,
     )]
-    pub to: Account<'info, TokenAccount>,
+    pub reflection_vault: Account<'info, TokenAccount>,
 
     pub token_program: Program<'info, Token>,
 }
@@ -380,6 +1419,23 @@ pub struct ParticipateInLaunch<'info> {
 
     pub mint: Account<'info, Mint>,
 
+    /// CHECK: configuración del token en su PDA; puede no existir para mints sin configuración
+    #[account(
+        seeds = [b"token_config", mint.key().as_ref()],
+        bump
+    )]
+    pub token_config: UncheckedAccount<'info>,
+
+    /// Cooldown del participante, requerido durante la ventana anti-sniper
+    #[account(
+        init_if_needed,
+        payer = participant,
+        space = 8 + WalletCooldown::INIT_SPACE,
+        seeds = [b"wallet_cooldown", mint.key().as_ref(), participant.key().as_ref()],
+        bump
+    )]
+    pub wallet_cooldown: Option<Account<'info, WalletCooldown>>,
+
     #[account(
         mut,
         token::mint = mint,
@@ -417,9 +1473,286 @@ pub struct DeflationaryTokenConfig {
     pub total_burned: u64,
     pub created_at: i64,
     pub is_active: bool,
+    pub staking_pool: Pubkey, // pool de defla_staking que otorga descuentos
+    #[max_len(MAX_DISCOUNT_TIERS)]
+    pub staking_discount_tiers: Vec<StakingDiscountTier>,
+    pub burn_split: BurnSplit,
+    pub reward_vault: Pubkey, // token account del pool de staking
+    pub treasury: Pubkey, // token account del creador
+    pub total_to_stakers: u64,
+    pub total_to_treasury: u64,
+    pub reflection_per_token: u128, // escalado por REFLECTION_PRECISION
+    pub total_registered_balance: u64,
+    pub total_reflected: u64,
+    #[max_len(MAX_SCHEDULE_STEPS)]
+    pub burn_schedule: Vec<BurnScheduleStep>,
+    pub supply_floor: u64, // la quema se detiene al llegar a este supply
+    #[max_len(MAX_SIZE_TIERS)]
+    pub size_tiers: Vec<SizeTier>,
+    pub anti_sniper: AntiSniperConfig,
+    pub directional_rates: DirectionalRates,
+    pub total_burned_on_buys: u64,
+    pub total_burned_on_sells: u64,
+    pub pending_burn_rate: Option<u16>,
+    pub burn_rate_effective_at: i64,
+    pub pending_rates: Option<RateConfig>, // cambio de tasas programado, ver queue_rate_config
+    pub rates_effective_at: i64,
+    pub pending_authority: Option<Pubkey>,
+    pub launch_safety: LaunchSafetyOptions,
+    pub bump: u8,
+}
+
+impl DeflationaryTokenConfig {
+    // Tasa vigente: el último paso del calendario que se haya alcanzado, o burn_rate si ninguno;
+    // cero una vez que current_supply llega al suelo
+    pub fn effective_burn_rate(&self, current_time: i64) -> u16 {
+        if self.current_supply <= self.supply_floor {
+            return 0;
+        }
+
+        let elapsed = current_time - self.created_at;
+        self.burn_schedule
+            .iter()
+            .rev()
+            .find(|step| match step.trigger {
+                BurnScheduleTrigger::Elapsed(seconds) => elapsed >= seconds,
+                BurnScheduleTrigger::SupplyAtOrBelow(supply) => self.current_supply <= supply,
+            })
+            .map_or(self.base_burn_rate(current_time), |step| step.burn_rate)
+    }
+
+    // burn_rate contando un cambio programado cuyo timelock ya venció
+    pub fn base_burn_rate(&self, current_time: i64) -> u16 {
+        match self.pending_burn_rate {
+            Some(rate) if current_time >= self.burn_rate_effective_at => rate,
+            _ => self.burn_rate,
+        }
+    }
+
+    // Autoridades revocadas y metadata inmutable: lanzamiento seguro
+    pub fn is_safe_launch(&self) -> bool {
+        self.launch_safety.revoke_mint_authority
+            && self.launch_safety.revoke_freeze_authority
+            && self.launch_safety.immutable_metadata
+    }
+
+    // Guardar en burn_rate el cambio programado una vez vencido el timelock
+    pub fn apply_pending_burn_rate(&mut self, current_time: i64) {
+        if self.pending_burn_rate.is_some() && current_time >= self.burn_rate_effective_at {
+            self.burn_rate = self.base_burn_rate(current_time);
+            self.pending_burn_rate = None;
+        }
+    }
+
+    // Parámetros de tasa vigentes, sobre los que programar un cambio
+    pub fn rate_config(&mut self, current_time: i64) -> RateConfig {
+        self.apply_pending_rates(current_time);
+        RateConfig {
+            staking_pool: self.staking_pool,
+            staking_discount_tiers: self.staking_discount_tiers.clone(),
+            burn_schedule: self.burn_schedule.clone(),
+            supply_floor: self.supply_floor,
+            size_tiers: self.size_tiers.clone(),
+            directional_rates: self.directional_rates,
+            burn_split: self.burn_split,
+            reward_vault: self.reward_vault,
+            treasury: self.treasury,
+        }
+    }
+
+    // Programar parámetros de tasa; como burn_rate, entran en vigor pasado el timelock.
+    // Solo cabe un cambio programado a la vez, para que nada acorte ni alargue su espera.
+    // Devuelve el momento en que se aplican
+    pub fn queue_rate_config(&mut self, rates: RateConfig, current_time: i64) -> Result<i64> {
+        require!(self.pending_rates.is_none(), DeflationaryError::RateUpdatePending);
+        self.pending_rates = Some(rates);
+        self.rates_effective_at = current_time.checked_add(BURN_RATE_TIMELOCK).unwrap();
+        Ok(self.rates_effective_at)
+    }
+
+    // Guardar los parámetros de tasa programados una vez vencido el timelock
+    pub fn apply_pending_rates(&mut self, current_time: i64) {
+        if current_time < self.rates_effective_at {
+            return;
+        }
+        if let Some(rates) = self.pending_rates.take() {
+            self.staking_pool = rates.staking_pool;
+            self.staking_discount_tiers = rates.staking_discount_tiers;
+            self.burn_schedule = rates.burn_schedule;
+            self.supply_floor = rates.supply_floor;
+            self.size_tiers = rates.size_tiers;
+            self.directional_rates = rates.directional_rates;
+            self.burn_split = rates.burn_split;
+            self.reward_vault = rates.reward_vault;
+            self.treasury = rates.treasury;
+        }
+    }
+
+    // Tasa para una transferencia concreta: la de compra o venta si están activas, si no la
+    // vigente; o la del tier de tamaño alcanzado si es mayor. Devuelve también el índice del tier
+    pub fn transfer_burn_rate(
+        &self,
+        amount: u64,
+        direction: TransferDirection,
+        current_time: i64,
+    ) -> (u16, Option<u8>) {
+        if self.current_supply <= self.supply_floor {
+            return (0, None);
+        }
+
+        let rates = &self.directional_rates;
+        let base_rate = match direction {
+            TransferDirection::Buy if rates.enabled => rates.buy_burn_rate,
+            TransferDirection::Sell if rates.enabled => rates.sell_burn_rate,
+            _ => self.effective_burn_rate(current_time),
+        };
+
+        let size_bps = amount as u128 * BASIS_POINTS as u128 / self.current_supply.max(1) as u128;
+        match self
+            .size_tiers
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, tier)| size_bps >= tier.min_supply_bps as u128)
+        {
+            Some((index, tier)) if tier.burn_rate > base_rate => (tier.burn_rate, Some(index as u8)),
+            _ => (base_rate, None),
+        }
+    }
+}
+
+// Parámetros que cambian la tasa de quema de una transferencia, programados con timelock
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct RateConfig {
+    pub staking_pool: Pubkey,
+    #[max_len(MAX_DISCOUNT_TIERS)]
+    pub staking_discount_tiers: Vec<StakingDiscountTier>,
+    #[max_len(MAX_SCHEDULE_STEPS)]
+    pub burn_schedule: Vec<BurnScheduleStep>,
+    pub supply_floor: u64,
+    #[max_len(MAX_SIZE_TIERS)]
+    pub size_tiers: Vec<SizeTier>,
+    pub directional_rates: DirectionalRates,
+    pub burn_split: BurnSplit,
+    pub reward_vault: Pubkey,
+    pub treasury: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum BurnScheduleTrigger {
+    Elapsed(i64), // segundos desde created_at
+    SupplyAtOrBelow(u64),
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct LaunchSafetyOptions {
+    pub revoke_mint_authority: bool,
+    pub revoke_freeze_authority: bool,
+    pub immutable_metadata: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct DirectionalRates {
+    pub enabled: bool,
+    pub buy_burn_rate: u16, // basis points
+    pub sell_burn_rate: u16, // basis points
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TransferDirection {
+    Transfer,
+    Buy,  // desde un pool registrado
+    Sell, // hacia un pool registrado
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct AntiSniperConfig {
+    pub window_start: i64,
+    pub window_end: i64, // las protecciones se apagan solas a partir de aquí
+    pub max_tx_amount: u64, // 0 = sin límite
+    pub cooldown_seconds: i64, // 0 = sin cooldown
+}
+
+impl AntiSniperConfig {
+    pub fn is_active(&self, current_time: i64) -> bool {
+        current_time >= self.window_start && current_time < self.window_end
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct AntiSniperParams {
+    pub window_seconds: i64,
+    pub from_launch_start: bool, // si no, desde created_at
+    pub max_tx_amount: u64,
+    pub cooldown_seconds: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct SizeTier {
+    pub min_supply_bps: u16, // tamaño mínimo de la transferencia, en bps de current_supply
+    pub burn_rate: u16, // basis points
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct BurnScheduleStep {
+    pub trigger: BurnScheduleTrigger,
+    pub burn_rate: u16, // basis points
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct WalletCooldown {
+    pub mint: Pubkey,
+    pub wallet: Pubkey,
+    pub last_transfer_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PoolVault {
+    pub mint: Pubkey,
+    pub vault: Pubkey, // token account del pool de liquidez
+    pub added_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct BurnExemption {
+    pub mint: Pubkey,
+    pub address: Pubkey, // dueño de las token accounts exentas (vault, pool, PDA)
+    pub added_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct HolderRewards {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub balance: u64, // saldo registrado en la última sincronización
+    pub reward_debt: u128,
+    pub pending_rewards: u64,
+    pub total_claimed: u64,
     pub bump: u8,
 }
 
+// Reparto de la cantidad deducida; lo que no va a stakers, tesorería ni reflection se quema
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct BurnSplit {
+    pub staking_bps: u16,
+    pub treasury_bps: u16,
+    pub reflection_bps: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct StakingDiscountTier {
+    pub min_staked: u64,
+    pub discount_bps: u16, // porcentaje del burn_rate que se descuenta
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct LaunchConfig {
@@ -444,6 +1777,9 @@ pub struct TokenCreatedEvent {
     pub symbol: String,
     pub initial_supply: u64,
     pub burn_rate: u16,
+    pub decimals: u8,
+    pub launch_safety: LaunchSafetyOptions,
+    pub safe_launch: bool, // ver DeflationaryTokenConfig::is_safe_launch
     pub timestamp: i64,
 }
 
@@ -454,6 +1790,145 @@ pub struct TransferWithBurnEvent {
     pub to: Pubkey,
     pub amount_transferred: u64,
     pub amount_burned: u64,
+    pub amount_to_stakers: u64,
+    pub amount_to_treasury: u64,
+    pub amount_reflected: u64,
+    pub burn_rate: u16, // tasa aplicada tras calendario, descuentos y exenciones
+    pub size_tier: Option<u8>,
+    pub direction: TransferDirection,
+    pub discount_bps: u16,
+    pub exempt: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenActiveUpdatedEvent {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub is_active: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BurnRateUpdateQueuedEvent {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub current_burn_rate: u16,
+    pub new_burn_rate: u16,
+    pub effective_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityProposedEvent {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub proposed_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferredEvent {
+    pub mint: Pubkey,
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DirectionalRatesUpdatedEvent {
+    pub mint: Pubkey,
+    pub enabled: bool,
+    pub buy_burn_rate: u16,
+    pub sell_burn_rate: u16,
+    pub effective_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolVaultUpdatedEvent {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub registered: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AntiSniperUpdatedEvent {
+    pub mint: Pubkey,
+    pub window_start: i64,
+    pub window_end: i64,
+    pub max_tx_amount: u64,
+    pub cooldown_seconds: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SizeTiersUpdatedEvent {
+    pub mint: Pubkey,
+    pub size_tiers: Vec<SizeTier>,
+    pub effective_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BurnScheduleUpdatedEvent {
+    pub mint: Pubkey,
+    pub burn_schedule: Vec<BurnScheduleStep>,
+    pub supply_floor: u64,
+    pub effective_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BurnExemptionUpdatedEvent {
+    pub mint: Pubkey,
+    pub address: Pubkey,
+    pub exempt: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct HolderRegisteredEvent {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct HolderRewardsClaimedEvent {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RateUpdateCancelledEvent {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BurnSplitUpdatedEvent {
+    pub mint: Pubkey,
+    pub staking_bps: u16,
+    pub treasury_bps: u16,
+    pub reflection_bps: u16,
+    pub reward_vault: Pubkey,
+    pub treasury: Pubkey,
+    pub effective_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakingDiscountTiersUpdatedEvent {
+    pub mint: Pubkey,
+    pub staking_pool: Pubkey,
+    pub tiers: Vec<StakingDiscountTier>,
+    pub effective_at: i64,
     pub timestamp: i64,
 }
 
@@ -486,4 +1961,380 @@ pub enum DeflationaryError {
     LaunchEnded,
     #[msg("Not enough tokens left for sale")]
     NotEnoughTokensLeft,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Reward vault is not the staking pool's token account for this mint")]
+    InvalidRewardVault,
+    #[msg("A rate update is already queued")]
+    RateUpdatePending,
+    #[msg("No rate update is queued")]
+    NoPendingRateUpdate,
+    #[msg("Invalid staking discount tiers")]
+    InvalidDiscountTiers,
+    #[msg("Stake account does not belong to the sender in the configured pool")]
+    InvalidStakeAccount,
+    #[msg("Tokenized stake positions do not qualify for the staking discount")]
+    TokenizedStakeAccount,
+    #[msg("Invalid burn split")]
+    InvalidBurnSplit,
+    #[msg("Reward vault or treasury account missing for the configured burn split")]
+    MissingSplitAccount,
+    #[msg("Holder rewards account does not match the token account")]
+    InvalidHolderAccount,
+    #[msg("No holder rewards to claim")]
+    NoHolderRewards,
+    #[msg("Invalid burn schedule")]
+    InvalidBurnSchedule,
+    #[msg("Invalid transfer size tiers")]
+    InvalidSizeTiers,
+    #[msg("Invalid anti-sniper configuration")]
+    InvalidAntiSniperConfig,
+    #[msg("Amount exceeds the maximum per transaction during the launch window")]
+    MaxTransactionExceeded,
+    #[msg("Wallet cooldown account is required during the launch window")]
+    CooldownAccountRequired,
+    #[msg("Wallet is still in cooldown")]
+    WalletCooldownActive,
+}
+
+// Aplicar el límite por transacción y el cooldown por wallet mientras dure la ventana anti-sniper
+fn enforce_anti_sniper(
+    anti_sniper: &AntiSniperConfig,
+    wallet_cooldown: Option<&mut WalletCooldown>,
+    cooldown_bump: Option<u8>,
+    wallet: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    current_time: i64,
+) -> Result<()> {
+    if !anti_sniper.is_active(current_time) {
+        return Ok(());
+    }
+
+    require!(
+        anti_sniper.max_tx_amount == 0 || amount <= anti_sniper.max_tx_amount,
+        DeflationaryError::MaxTransactionExceeded
+    );
+
+    if anti_sniper.cooldown_seconds > 0 {
+        let wallet_cooldown = wallet_cooldown.ok_or(DeflationaryError::CooldownAccountRequired)?;
+        if wallet_cooldown.wallet == Pubkey::default() {
+            wallet_cooldown.mint = mint;
+            wallet_cooldown.wallet = wallet;
+            wallet_cooldown.bump = cooldown_bump.unwrap();
+        } else {
+            require!(
+                current_time >= wallet_cooldown.last_transfer_at + anti_sniper.cooldown_seconds,
+                DeflationaryError::WalletCooldownActive
+            );
+        }
+        wallet_cooldown.last_transfer_at = current_time;
+    }
+
+    Ok(())
+}
+
+// Una PDA del programa existe si tiene datos y pertenece a este programa
+fn is_initialized(info: &AccountInfo) -> bool {
+    info.owner == &crate::ID && !info.data_is_empty()
+}
+
+// Leer la configuración del token si el mint la tiene
+fn load_token_config(info: &AccountInfo) -> Result<Option<DeflationaryTokenConfig>> {
+    if !is_initialized(info) {
+        return Ok(None);
+    }
+    Ok(Some(DeflationaryTokenConfig::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+}
+
+// Leer la cuenta de reflection si está registrada para esa token account
+fn load_holder_rewards(info: &AccountInfo, token_account: Pubkey) -> Result<Option<HolderRewards>> {
+    if !is_initialized(info) {
+        return Ok(None);
+    }
+    let holder_rewards = HolderRewards::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    Ok((holder_rewards.token_account == token_account).then_some(holder_rewards))
+}
+
+// Guardar la cuenta de reflection sincronizada
+fn store_holder_rewards(info: &AccountInfo, holder_rewards: &HolderRewards) -> Result<()> {
+    holder_rewards.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+}
+
+// Liquidar lo acumulado por el holder y registrar su nuevo saldo. Solo acumula sobre
+// lo que sigue teniendo: los tokens movidos fuera del programa no generan reflection
+fn sync_holder(token_config: &mut DeflationaryTokenConfig, holder_rewards: &mut HolderRewards, balance: u64) {
+    let base = holder_rewards.balance.min(balance);
+    let accrued = base as u128 * token_config.reflection_per_token / REFLECTION_PRECISION;
+    let debt = if holder_rewards.balance == 0 {
+        0
+    } else {
+        holder_rewards.reward_debt * base as u128 / holder_rewards.balance as u128
+    };
+    let pending = accrued.saturating_sub(debt) as u64;
+    holder_rewards.pending_rewards = holder_rewards.pending_rewards.checked_add(pending).unwrap();
+
+    token_config.total_registered_balance = token_config
+        .total_registered_balance
+        .checked_sub(holder_rewards.balance)
+        .unwrap()
+        .checked_add(balance)
+        .unwrap();
+    holder_rewards.balance = balance;
+    holder_rewards.reward_debt = balance as u128 * token_config.reflection_per_token / REFLECTION_PRECISION;
+}
+
+// Descuento del tier más alto alcanzado por la cantidad en staking
+// Validar el calendario: cada tipo de disparador debe ir en el orden en que se alcanza
+fn validate_burn_schedule(burn_schedule: &[BurnScheduleStep]) -> Result<()> {
+    require!(burn_schedule.len() <= MAX_SCHEDULE_STEPS, DeflationaryError::InvalidBurnSchedule);
+    let mut last_elapsed: Option<i64> = None;
+    let mut last_supply: Option<u64> = None;
+    for step in burn_schedule.iter() {
+        require!(step.burn_rate <= MAX_BURN_RATE, DeflationaryError::BurnRateTooHigh);
+        match step.trigger {
+            BurnScheduleTrigger::Elapsed(seconds) => {
+                require!(
+                    seconds >= 0 && last_elapsed.is_none_or(|last| seconds > last),
+                    DeflationaryError::InvalidBurnSchedule
+                );
+                last_elapsed = Some(seconds);
+            }
+            BurnScheduleTrigger::SupplyAtOrBelow(supply) => {
+                require!(
+                    last_supply.is_none_or(|last| supply < last),
+                    DeflationaryError::InvalidBurnSchedule
+                );
+                last_supply = Some(supply);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn staking_discount_bps(tiers: &[StakingDiscountTier], staked_amount: u64) -> u16 {
+    tiers
+        .iter()
+        .rev()
+        .find(|tier| staked_amount >= tier.min_staked)
+        .map_or(0, |tier| tier.discount_bps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUPPLY: u64 = 1_000_000;
+
+    fn token_config() -> DeflationaryTokenConfig {
+        DeflationaryTokenConfig {
+            authority: Pubkey::default(),
+            mint: Pubkey::default(),
+            name: String::new(),
+            symbol: String::new(),
+            decimals: 9,
+            burn_rate: 200,
+            initial_supply: SUPPLY,
+            max_supply: SUPPLY,
+            current_supply: SUPPLY,
+            total_burned: 0,
+            created_at: 0,
+            is_active: true,
+            staking_pool: Pubkey::default(),
+            staking_discount_tiers: Vec::new(),
+            burn_split: BurnSplit::default(),
+            reward_vault: Pubkey::default(),
+            treasury: Pubkey::default(),
+            total_to_stakers: 0,
+            total_to_treasury: 0,
+            reflection_per_token: 0,
+            total_registered_balance: 0,
+            total_reflected: 0,
+            burn_schedule: Vec::new(),
+            supply_floor: 0,
+            size_tiers: Vec::new(),
+            anti_sniper: AntiSniperConfig::default(),
+            directional_rates: DirectionalRates::default(),
+            total_burned_on_buys: 0,
+            total_burned_on_sells: 0,
+            pending_burn_rate: None,
+            burn_rate_effective_at: 0,
+            pending_rates: None,
+            rates_effective_at: 0,
+            pending_authority: None,
+            launch_safety: LaunchSafetyOptions::default(),
+            bump: 0,
+        }
+    }
+
+    fn holder() -> HolderRewards {
+        HolderRewards {
+            owner: Pubkey::default(),
+            mint: Pubkey::default(),
+            token_account: Pubkey::default(),
+            balance: 0,
+            reward_debt: 0,
+            pending_rewards: 0,
+            total_claimed: 0,
+            bump: 0,
+        }
+    }
+
+    fn step(trigger: BurnScheduleTrigger, burn_rate: u16) -> BurnScheduleStep {
+        BurnScheduleStep { trigger, burn_rate }
+    }
+
+    #[test]
+    fn sync_holder_accrues_on_the_lower_of_registered_and_live_balance() {
+        let mut config = token_config();
+        let mut holder_rewards = holder();
+        sync_holder(&mut config, &mut holder_rewards, 1000);
+        assert_eq!((holder_rewards.balance, holder_rewards.pending_rewards), (1000, 0));
+        assert_eq!(config.total_registered_balance, 1000);
+
+        // 0,1 tokens de reflection por token registrado
+        config.reflection_per_token += REFLECTION_PRECISION / 10;
+        sync_holder(&mut config, &mut holder_rewards, 1000);
+        assert_eq!(holder_rewards.pending_rewards, 100);
+        assert_eq!(holder_rewards.reward_debt, 100);
+
+        // Tokens sacados fuera del programa no generan reflection; la deuda se reescala
+        config.reflection_per_token += REFLECTION_PRECISION / 10;
+        sync_holder(&mut config, &mut holder_rewards, 400);
+        assert_eq!(holder_rewards.pending_rewards, 140);
+        assert_eq!((holder_rewards.balance, holder_rewards.reward_debt), (400, 80));
+        assert_eq!(config.total_registered_balance, 400);
+
+        // Tokens recibidos fuera del programa tampoco cuentan hasta la siguiente sincronización
+        config.reflection_per_token += REFLECTION_PRECISION / 10;
+        sync_holder(&mut config, &mut holder_rewards, 1000);
+        assert_eq!(holder_rewards.pending_rewards, 180);
+        assert_eq!((holder_rewards.balance, holder_rewards.reward_debt), (1000, 300));
+        assert_eq!(config.total_registered_balance, 1000);
+    }
+
+    #[test]
+    fn staking_discount_picks_the_highest_tier_reached() {
+        let tiers = [
+            StakingDiscountTier { min_staked: 100, discount_bps: 1000 },
+            StakingDiscountTier { min_staked: 1000, discount_bps: 5000 },
+        ];
+
+        assert_eq!(staking_discount_bps(&[], 5000), 0);
+        assert_eq!(staking_discount_bps(&tiers, 99), 0);
+        assert_eq!(staking_discount_bps(&tiers, 100), 1000);
+        assert_eq!(staking_discount_bps(&tiers, 999), 1000);
+        assert_eq!(staking_discount_bps(&tiers, 5000), 5000);
+    }
+
+    #[test]
+    fn effective_burn_rate_follows_schedule_and_floor() {
+        let mut config = token_config();
+        config.burn_schedule = vec![
+            step(BurnScheduleTrigger::Elapsed(100), 300),
+            step(BurnScheduleTrigger::SupplyAtOrBelow(900_000), 400),
+        ];
+
+        assert_eq!(config.effective_burn_rate(50), 200);
+        assert_eq!(config.effective_burn_rate(100), 300);
+        config.current_supply = 900_000;
+        assert_eq!(config.effective_burn_rate(50), 400);
+
+        // Sin quema al llegar al suelo de supply
+        config.supply_floor = 900_000;
+        assert_eq!(config.effective_burn_rate(50), 0);
+
+        // Sin calendario rige burn_rate, contando el cambio programado ya vencido
+        let mut config = token_config();
+        config.pending_burn_rate = Some(500);
+        config.burn_rate_effective_at = 10;
+        assert_eq!(config.effective_burn_rate(5), 200);
+        assert_eq!(config.effective_burn_rate(10), 500);
+    }
+
+    #[test]
+    fn burn_schedule_steps_must_be_ordered_per_trigger() {
+        use BurnScheduleTrigger::{Elapsed, SupplyAtOrBelow};
+
+        assert!(validate_burn_schedule(&[
+            step(Elapsed(10), 100),
+            step(SupplyAtOrBelow(900), 200),
+            step(Elapsed(20), 300),
+            step(SupplyAtOrBelow(800), 400),
+        ])
+        .is_ok());
+        assert!(validate_burn_schedule(&[step(Elapsed(-1), 100)]).is_err());
+        assert!(validate_burn_schedule(&[step(Elapsed(10), 100), step(Elapsed(10), 200)]).is_err());
+        assert!(validate_burn_schedule(&[step(SupplyAtOrBelow(900), 100), step(SupplyAtOrBelow(950), 200)]).is_err());
+        assert!(validate_burn_schedule(&[step(Elapsed(10), MAX_BURN_RATE + 1)]).is_err());
+    }
+
+    #[test]
+    fn transfer_burn_rate_picks_direction_then_size_tier() {
+        let mut config = token_config();
+        config.size_tiers = vec![
+            SizeTier { min_supply_bps: 100, burn_rate: 500 },
+            SizeTier { min_supply_bps: 500, burn_rate: 800 },
+        ];
+        config.directional_rates = DirectionalRates { enabled: true, buy_burn_rate: 50, sell_burn_rate: 600 };
+
+        assert_eq!(config.transfer_burn_rate(1_000, TransferDirection::Transfer, 0), (200, None));
+        assert_eq!(config.transfer_burn_rate(10_000, TransferDirection::Transfer, 0), (500, Some(0)));
+        assert_eq!(config.transfer_burn_rate(50_000, TransferDirection::Transfer, 0), (800, Some(1)));
+        assert_eq!(config.transfer_burn_rate(1_000, TransferDirection::Buy, 0), (50, None));
+        assert_eq!(config.transfer_burn_rate(10_000, TransferDirection::Buy, 0), (500, Some(0)));
+        // El tier solo se aplica si supera la tasa de venta
+        assert_eq!(config.transfer_burn_rate(10_000, TransferDirection::Sell, 0), (600, None));
+        assert_eq!(config.transfer_burn_rate(50_000, TransferDirection::Sell, 0), (800, Some(1)));
+
+        config.directional_rates.enabled = false;
+        assert_eq!(config.transfer_burn_rate(1_000, TransferDirection::Sell, 0), (200, None));
+
+        config.supply_floor = SUPPLY;
+        assert_eq!(config.transfer_burn_rate(50_000, TransferDirection::Sell, 0), (0, None));
+    }
+
+    #[test]
+    fn anti_sniper_limits_apply_only_inside_the_window() {
+        let anti_sniper = AntiSniperConfig { window_start: 100, window_end: 200, max_tx_amount: 1000, cooldown_seconds: 30 };
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let mut cooldown = WalletCooldown { mint: Pubkey::default(), wallet: Pubkey::default(), last_transfer_at: 0, bump: 0 };
+
+        // Fuera de la ventana no hay límites ni hace falta la cuenta de cooldown
+        assert!(enforce_anti_sniper(&anti_sniper, None, None, wallet, mint, 5000, 99).is_ok());
+        assert!(enforce_anti_sniper(&anti_sniper, None, None, wallet, mint, 5000, 200).is_ok());
+
+        assert!(enforce_anti_sniper(&anti_sniper, Some(&mut cooldown), Some(1), wallet, mint, 1001, 100).is_err());
+        assert!(enforce_anti_sniper(&anti_sniper, None, None, wallet, mint, 1000, 100).is_err());
+
+        enforce_anti_sniper(&anti_sniper, Some(&mut cooldown), Some(1), wallet, mint, 1000, 100).unwrap();
+        assert_eq!((cooldown.wallet, cooldown.mint, cooldown.last_transfer_at), (wallet, mint, 100));
+
+        assert!(enforce_anti_sniper(&anti_sniper, Some(&mut cooldown), Some(1), wallet, mint, 10, 129).is_err());
+        enforce_anti_sniper(&anti_sniper, Some(&mut cooldown), Some(1), wallet, mint, 10, 130).unwrap();
+        assert_eq!(cooldown.last_transfer_at, 130);
+    }
+
+    #[test]
+    fn rate_updates_wait_for_the_timelock_one_at_a_time() {
+        let mut config = token_config();
+        let mut rates = config.rate_config(0);
+        rates.size_tiers = vec![SizeTier { min_supply_bps: 100, burn_rate: 500 }];
+        rates.burn_split.treasury_bps = 1000;
+
+        assert_eq!(config.queue_rate_config(rates.clone(), 0).unwrap(), BURN_RATE_TIMELOCK);
+        assert!(config.queue_rate_config(rates.clone(), 10).is_err());
+
+        config.apply_pending_rates(BURN_RATE_TIMELOCK - 1);
+        assert!(config.size_tiers.is_empty());
+        assert_eq!(config.burn_split.treasury_bps, 0);
+
+        let live = config.rate_config(BURN_RATE_TIMELOCK);
+        assert!(config.pending_rates.is_none());
+        assert_eq!((live.size_tiers.len(), live.burn_split.treasury_bps), (1, 1000));
+        assert_eq!(config.transfer_burn_rate(10_000, TransferDirection::Transfer, BURN_RATE_TIMELOCK), (500, Some(0)));
+
+        assert!(config.queue_rate_config(rates, BURN_RATE_TIMELOCK).is_ok());
+    }
 }